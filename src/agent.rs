@@ -1,30 +1,153 @@
-use bevy::color::palettes::tailwind as tw;
 use bevy::prelude::*;
 
-use crate::grid::GridCell;
+use crate::{
+  entropy::{AgentSpawning, RngStream},
+  grid::{GridBounds, GridCell},
+  interpolation::Interpolated,
+  memory::AgentMemory,
+  schedule::{PrepareTickSet, SimulationTick},
+  terrain::StepCooldown,
+  walls::Walkability,
+};
 
 pub fn agent_plugin(app: &mut App) {
-  app.add_observer(spawn_agent);
+  app
+    .init_resource::<SelectedAgent>()
+    .add_systems(SimulationTick, update_facing.in_set(PrepareTickSet::Facing))
+    .add_observer(spawn_agent)
+    .add_observer(select_next_agent)
+    .add_observer(cycle_selected_agent_group);
 }
 
 fn spawn_agent(
   _trigger: Trigger<SpawnAgent>,
+  walkability: Walkability,
   mut commands: Commands,
-  mut r_meshes: ResMut<Assets<Mesh>>,
-  mut r_materials: ResMut<Assets<ColorMaterial>>,
+  mut rng: ResMut<RngStream<AgentSpawning>>,
 ) {
-  commands.spawn((
-    Agent,
-    Mesh2d(r_meshes.add(Rectangle::new(0.9, 0.9))),
-    MeshMaterial2d(r_materials.add(Color::from(tw::GREEN_600))),
-  ));
+  let mut cell = GridCell::default();
+  if !walkability.is_walkable(&cell) {
+    // the center is walled off, so drop the agent somewhere else
+    let Some(random_cell) = walkability.get_random_position(&mut *rng) else {
+      warn!("could not find a free cell to spawn an agent in");
+      return;
+    };
+    cell = random_cell;
+  }
+
+  commands.spawn((Agent, cell));
+}
+
+/// Cycles the selection through all agents.
+fn select_next_agent(
+  _trigger: Trigger<SelectNextAgent>,
+  q_agents: Query<Entity, With<Agent>>,
+  mut r_selected_agent: ResMut<SelectedAgent>,
+) {
+  let mut agents = q_agents.iter().collect::<Vec<_>>();
+  agents.sort();
+  let next = match r_selected_agent.0 {
+    Some(selected) => agents
+      .iter()
+      .find(|&&agent| agent > selected)
+      .or(agents.first()),
+    None => agents.first(),
+  };
+  r_selected_agent.0 = next.copied();
+}
+
+/// Moves the selected agent to the next group (and out of the last one).
+fn cycle_selected_agent_group(
+  _trigger: Trigger<CycleSelectedAgentGroup>,
+  q_groups: Query<Option<&AgentGroup>, With<Agent>>,
+  r_selected_agent: Res<SelectedAgent>,
+  mut commands: Commands,
+) {
+  let Some((agent, group)) = r_selected_agent
+    .0
+    .and_then(|agent| Some((agent, q_groups.get(agent).ok()?)))
+  else {
+    return;
+  };
+  match group {
+    None => {
+      commands.entity(agent).insert(AgentGroup(1));
+    }
+    Some(AgentGroup(group)) if *group < AGENT_GROUPS => {
+      commands.entity(agent).insert(AgentGroup(group + 1));
+    }
+    Some(_) => {
+      commands.entity(agent).remove::<AgentGroup>();
+    }
+  }
+}
+
+/// Turns agents towards the direction they last stepped in.
+fn update_facing(
+  mut q_agents: Query<(&GridCell, &mut Facing), Changed<GridCell>>,
+  r_grid_bounds: Res<GridBounds>,
+) {
+  for (cell, mut facing) in q_agents.iter_mut() {
+    // (stepping across the edges in wrap mode is still a single step)
+    let step = r_grid_bounds.displacement(&facing.last_cell, cell);
+    // anything further than a diagonal step was a jump (spawning, resizing), not a step
+    if step != Vec2::ZERO && step.length() < 1.5 {
+      facing.direction = step.normalize();
+    }
+    facing.last_cell = *cell;
+  }
 }
 
 #[derive(Component)]
 // bevy 0.16 syntax
 // #[require(Transform::from_xyz(0.0, 0.0, 0.1), GridCell)]
-#[require(Transform(|| Transform::from_xyz(0.0, 0.0, 0.1)), GridCell)]
+#[require(
+  Transform(|| Transform::from_xyz(0.0, 0.0, 0.1)),
+  GridCell,
+  StepCooldown,
+  Facing,
+  AgentMemory,
+  Interpolated
+)]
 pub struct Agent;
 
+/// The direction an agent is looking in.
+#[derive(Component)]
+pub struct Facing {
+  direction: Vec2,
+  last_cell: GridCell,
+}
+
+impl Facing {
+  pub fn direction(&self) -> Vec2 {
+    self.direction
+  }
+}
+
+impl Default for Facing {
+  fn default() -> Self {
+    Self {
+      direction: Vec2::X,
+      last_cell: GridCell::default(),
+    }
+  }
+}
+
+/// A group that agents can be put in, to give them a behaviour all at once (numbered from 1).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgentGroup(pub u8);
+
+const AGENT_GROUPS: u8 = 3;
+
+/// The agent that overlays (like the fog of war) are shown for.
+#[derive(Resource, Default, PartialEq)]
+pub struct SelectedAgent(pub Option<Entity>);
+
 #[derive(Event)]
 pub struct SpawnAgent;
+
+#[derive(Event)]
+pub struct SelectNextAgent;
+
+#[derive(Event)]
+pub struct CycleSelectedAgentGroup;
@@ -0,0 +1,327 @@
+use bevy::asset::UntypedAssetId;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_behave::prelude::*;
+use rand::{Rng, distributions::WeightedIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  agent::{Agent, AgentGroup},
+  entropy::{BehaviourAssignment, RngStream},
+  schedule::{PrepareTickSet, SimulationTick},
+};
+
+use super::{
+  BehaviourTreeAsset, BehaviourTreeSource, MovementBehaviour, WalkInDirectionUntilOutOfBounds,
+};
+
+pub fn assignment_plugin(app: &mut App) {
+  app
+    .init_resource::<MovementBehaviourTrees>()
+    .init_resource::<SpawnBehaviours>()
+    .add_systems(
+      SimulationTick,
+      (assign_spawned_agents, apply_assigned_behaviours)
+        .chain()
+        .in_set(PrepareTickSet::MovementBehaviour),
+    )
+    .add_observer(on_assign_movement_behaviour)
+    .add_observer(on_set_spawn_behaviours);
+}
+
+fn on_assign_movement_behaviour(
+  trigger: Trigger<AssignMovementBehaviour>,
+  q_agents: Query<(Entity, Option<&AgentGroup>), With<Agent>>,
+  mut r_spawn_behaviours: ResMut<SpawnBehaviours>,
+  mut commands: Commands,
+) {
+  let AssignMovementBehaviour { behaviour, to } = *trigger.event();
+  if to == Assignees::All {
+    // (agents that spawn later get it too)
+    r_spawn_behaviours.0 = MovementBehaviourWeights::only(behaviour);
+  }
+
+  for (agent, group) in q_agents.iter() {
+    let assign = match to {
+      Assignees::All => true,
+      Assignees::Agent(assignee) => agent == assignee,
+      Assignees::Group(assignees) => group == Some(&assignees),
+    };
+    if assign {
+      commands
+        .entity(agent)
+        .insert(AssignedMovementBehaviour(behaviour));
+    }
+  }
+}
+
+fn on_set_spawn_behaviours(
+  trigger: Trigger<SetSpawnBehaviours>,
+  mut r_spawn_behaviours: ResMut<SpawnBehaviours>,
+) {
+  match trigger.event().0.validate() {
+    Ok(weights) => r_spawn_behaviours.0 = weights,
+    Err(error) => warn!("ignoring spawn behaviours: {}", error),
+  }
+}
+
+/// Draws a behaviour for every new agent that wasn't given one yet (if any behaviour has a weight).
+fn assign_spawned_agents(
+  q_new_agents: Query<Entity, (Added<Agent>, Without<AssignedMovementBehaviour>)>,
+  r_spawn_behaviours: Res<SpawnBehaviours>,
+  mut rng: ResMut<RngStream<BehaviourAssignment>>,
+  mut commands: Commands,
+) {
+  for agent in q_new_agents.iter() {
+    if let Some(behaviour) = r_spawn_behaviours.0.sample(&mut *rng) {
+      commands
+        .entity(agent)
+        .insert(AssignedMovementBehaviour(behaviour));
+    }
+  }
+}
+
+/// Makes every agent move the way it was assigned: it walks naively, or it gets the tree of its
+/// behaviour (which replaces the tree of the behaviour it had before, or a tree that was reloaded).
+fn apply_assigned_behaviours(
+  q_agents: Query<
+    (
+      Entity,
+      &AssignedMovementBehaviour,
+      Option<&Children>,
+      Has<WalkInDirectionUntilOutOfBounds>,
+    ),
+    With<Agent>,
+  >,
+  q_movement_behaviours: Query<&BehaviourTreeSource, With<MovementBehaviour>>,
+  r_movement_behaviour_trees: Res<MovementBehaviourTrees>,
+  r_behaviour_trees: Res<Assets<BehaviourTreeAsset>>,
+  mut commands: Commands,
+) {
+  for (agent, assigned, children, walks_naively) in q_agents.iter() {
+    let handle = r_movement_behaviour_trees.0.get(&assigned.0);
+
+    let naive = assigned.0 == MovementBehaviourKind::WalkLeftRightNaive;
+    if naive && !walks_naively {
+      commands
+        .entity(agent)
+        .insert(WalkInDirectionUntilOutOfBounds::new(-1, 0));
+    } else if !naive && walks_naively {
+      commands
+        .entity(agent)
+        .remove::<WalkInDirectionUntilOutOfBounds>();
+    }
+
+    let mut has_tree = false;
+    for child in children.into_iter().flatten() {
+      let Ok(source) = q_movement_behaviours.get(*child) else {
+        continue;
+      };
+      if !has_tree && handle.is_some_and(|handle| handle.id() == source.0) {
+        has_tree = true;
+      } else {
+        commands.entity(*child).despawn_recursive();
+      }
+    }
+
+    // (the tree is loaded from a file, so agents might have to wait a tick or two for it)
+    let Some(handle) = handle.filter(|_| !has_tree) else {
+      continue;
+    };
+    let Some(behaviour) = r_behaviour_trees.get(handle) else {
+      continue;
+    };
+    commands
+      .spawn((
+        Name::new(behaviour.name.clone()),
+        BehaveTree::new(behaviour.tree.clone()).with_logging(false),
+        BehaviourTreeSource(handle.id()),
+        MovementBehaviour,
+      ))
+      .set_parent(agent);
+  }
+}
+
+/// The trees of all movement behaviours, whether they are assigned to any agent yet or not.
+pub fn movement_behaviour_trees(world: &World) -> impl Iterator<Item = UntypedAssetId> + '_ {
+  world
+    .resource::<MovementBehaviourTrees>()
+    .0
+    .values()
+    .map(|handle| handle.id().untyped())
+}
+
+/// A way for agents to move around. Every agent has its own, so that they can be compared side by
+/// side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementBehaviourKind {
+  WalkLeftRightNaive,
+  WalkLeftRight,
+  WalkClockwise,
+  MoveToClosestFruit,
+  HungerBased,
+}
+
+impl MovementBehaviourKind {
+  pub const ALL: [Self; 5] = [
+    Self::WalkLeftRightNaive,
+    Self::WalkLeftRight,
+    Self::WalkClockwise,
+    Self::MoveToClosestFruit,
+    Self::HungerBased,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::WalkLeftRightNaive => "walk_left_right_naive",
+      Self::WalkLeftRight => "walk_left_right",
+      Self::WalkClockwise => "walk_clockwise",
+      Self::MoveToClosestFruit => "move_to_closest_fruit",
+      Self::HungerBased => "hunger_based",
+    }
+  }
+
+  /// The file its tree is loaded from (the naive behaviour doesn't have a tree, its agents walk by
+  /// themselves).
+  fn tree_path(self) -> Option<String> {
+    match self {
+      Self::WalkLeftRightNaive => None,
+      _ => Some(format!("behaviours/{}.behaviour.ron", self.name())),
+    }
+  }
+}
+
+/// How likely every movement behaviour is to be given to an agent when it spawns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovementBehaviourWeights([f32; MovementBehaviourKind::ALL.len()]);
+
+impl MovementBehaviourWeights {
+  /// Nothing, agents spawn without a behaviour (and stand still).
+  pub const NONE: Self = Self([0.0; MovementBehaviourKind::ALL.len()]);
+  /// Every behaviour as likely as the others.
+  pub const EVEN: Self = Self([1.0; MovementBehaviourKind::ALL.len()]);
+
+  pub fn only(behaviour: MovementBehaviourKind) -> Self {
+    Self::NONE.with(behaviour, 1.0)
+  }
+
+  pub fn with(mut self, behaviour: MovementBehaviourKind, weight: f32) -> Self {
+    self.0[behaviour as usize] = weight;
+    self
+  }
+
+  /// Reads the weights from a URL query string, e.g.
+  /// `?behaviours=hunger_based:3,walk_clockwise:1,move_to_closest_fruit` (a behaviour without a
+  /// weight has a weight of 1).
+  pub fn from_query(query: &str) -> Option<Self> {
+    let (_, value) = query
+      .trim_start_matches('?')
+      .split('&')
+      .filter_map(|pair| pair.split_once('='))
+      .find(|(key, _)| *key == "behaviours")?;
+
+    let mut weights = Self::NONE;
+    for entry in value.split(',') {
+      let (name, weight) = entry.split_once(':').unwrap_or((entry, "1"));
+      let behaviour = MovementBehaviourKind::ALL
+        .into_iter()
+        .find(|behaviour| behaviour.name() == name);
+      match (behaviour, weight.parse::<f32>()) {
+        (Some(behaviour), Ok(weight)) if is_valid_weight(weight) => {
+          weights = weights.with(behaviour, weight)
+        }
+        _ => warn!("ignoring invalid behaviour weight {:?}", entry),
+      }
+    }
+    weights
+      .validate()
+      .inspect_err(|error| warn!("ignoring behaviours {:?}: {}", value, error))
+      .ok()
+  }
+
+  /// Checks that behaviours can be drawn with these weights: they are all finite and not negative,
+  /// and their total is finite and not zero.
+  pub fn validate(self) -> Result<Self, String> {
+    if let Some(index) = self.0.iter().position(|weight| !is_valid_weight(*weight)) {
+      let name = MovementBehaviourKind::ALL[index].name();
+      return Err(format!(
+        "the weight of {name} is not a finite, non-negative number"
+      ));
+    }
+    let total: f32 = self.0.iter().sum();
+    if !total.is_finite() {
+      return Err("the weights add up to more than can be represented".to_string());
+    }
+    if total == 0.0 {
+      return Err("no behaviour has a weight".to_string());
+    }
+    Ok(self)
+  }
+
+  fn sample(&self, rng: &mut impl Rng) -> Option<MovementBehaviourKind> {
+    // (agents spawn without a behaviour until they are given one)
+    if *self == Self::NONE {
+      return None;
+    }
+    match WeightedIndex::new(self.0) {
+      Ok(index) => Some(MovementBehaviourKind::ALL[rng.sample(index)]),
+      Err(error) => {
+        warn!("could not draw a behaviour from {:?}: {}", self, error);
+        None
+      }
+    }
+  }
+}
+
+fn is_valid_weight(weight: f32) -> bool {
+  weight.is_finite() && weight >= 0.0
+}
+
+/// The movement behaviour an agent was given.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssignedMovementBehaviour(pub MovementBehaviourKind);
+
+/// The behaviours that new agents are given.
+#[derive(Resource)]
+pub struct SpawnBehaviours(pub MovementBehaviourWeights);
+
+impl Default for SpawnBehaviours {
+  fn default() -> Self {
+    Self(MovementBehaviourWeights::NONE)
+  }
+}
+
+#[derive(Resource)]
+struct MovementBehaviourTrees(HashMap<MovementBehaviourKind, Handle<BehaviourTreeAsset>>);
+
+impl FromWorld for MovementBehaviourTrees {
+  fn from_world(world: &mut World) -> Self {
+    let r_asset_server = world.resource::<AssetServer>();
+    Self(
+      MovementBehaviourKind::ALL
+        .into_iter()
+        .filter_map(|behaviour| Some((behaviour, r_asset_server.load(behaviour.tree_path()?))))
+        .collect(),
+    )
+  }
+}
+
+/// Gives agents a movement behaviour (instead of the one they had).
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AssignMovementBehaviour {
+  pub behaviour: MovementBehaviourKind,
+  pub to: Assignees,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assignees {
+  /// Every agent, including the ones that spawn later.
+  All,
+  Agent(Entity),
+  Group(AgentGroup),
+}
+
+/// Sets how likely every behaviour is to be given to the agents that spawn from now on.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SetSpawnBehaviours(pub MovementBehaviourWeights);
@@ -1,70 +1,19 @@
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
+use serde::Deserialize;
 
-use crate::{
-  agent::Agent,
-  behaviours::target_finding::{FindTarget, GoToTarget, TargetKind},
-  hunger::Hunger,
-};
+use crate::{agent::Agent, hunger::Hunger};
 
-use super::{CurrentMovementBehaviour, MovementBehaviour};
+use super::LeafRegistry;
 
 pub fn hunger_based_plugin(app: &mut App) {
   app
-    .add_observer(enable_behaviour)
-    .add_observer(on_hunger_check);
-}
-
-fn build_behaviour_tree() -> Tree<bevy_behave::Behave> {
-  behave! {
-    Behave::Forever => {
-      Behave::Sequence => {
-        Behave::IfThen => {
-          Behave::trigger(HungerCheck(0.4)),
-
-          // spawned if hunger check succeeded
-          Behave::spawn((
-            Name::new("Find fruit"),
-            FindTarget::new(TargetKind::Fruit, 8),
-          )),
-
-          // spawned if hunger check failed
-          Behave::spawn((
-            Name::new("Find coins"),
-            FindTarget::new(TargetKind::Coins, 8),
-          )),
-        },
-
-        // go to the target we just found
-        Behave::spawn((
-          Name::new("Go to target"),
-          GoToTarget,
-        )),
-      }
-    }
-  }
-}
-
-fn enable_behaviour(
-  _trigger: Trigger<SetBehaviourHungerBased>,
-  q_agents: Query<Entity, With<Agent>>,
-  mut r_current_movement_behaviour: ResMut<CurrentMovementBehaviour>,
-  mut commands: Commands,
-) {
-  let tree = build_behaviour_tree();
-  let name = "Hunger based movement";
-
-  r_current_movement_behaviour.0 = Some((tree.clone(), name.into()));
-
-  for agent in q_agents.iter() {
-    commands
-      .spawn((
-        Name::new(name),
-        BehaveTree::new(tree.clone()).with_logging(false),
-        MovementBehaviour,
-      ))
-      .set_parent(agent);
-  }
+    .world()
+    .resource::<LeafRegistry>()
+    .register_with("HungerCheck", |hunger_check: HungerCheck, _name| {
+      Behave::trigger(hunger_check)
+    });
+  app.add_observer(on_hunger_check);
 }
 
 fn on_hunger_check(
@@ -77,7 +26,7 @@ fn on_hunger_check(
   let Ok(hunger) = q_agents.get(ctx.target_entity()) else {
     return;
   };
-  if hunger.fraction_left() < hunger_check.0 {
+  if hunger.fraction_left() < hunger_check.threshold {
     // agent is hungry -> report success for the check!
     commands.trigger(ctx.success());
   } else {
@@ -86,8 +35,8 @@ fn on_hunger_check(
   }
 }
 
-#[derive(Event, Clone)]
-struct HungerCheck(pub f32);
-
-#[derive(Event)]
-pub struct SetBehaviourHungerBased;
+/// Succeeds if the agent has less than `threshold` (as a fraction) of its food left.
+#[derive(Event, Clone, Deserialize)]
+struct HungerCheck {
+  threshold: f32,
+}
@@ -1,111 +1,75 @@
+mod assignment;
 mod hunger_based;
-mod move_to_closest_fruit;
 mod pickups;
 mod target_finding;
-mod walk_clockwise;
-mod walk_left_right;
+mod tree_assets;
 mod walk_left_right_naive;
 mod walking;
 
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
 
-pub use hunger_based::SetBehaviourHungerBased;
-pub use move_to_closest_fruit::SetBehaviourMoveToClosestFruit;
-pub use walk_clockwise::SetBehaviourWalkClockwise;
-pub use walk_left_right::SetBehaviourWalkLeftRight;
-pub use walk_left_right_naive::SetBehaviourWalkLeftRightNaive;
-use walking::WalkInDirectionUntilOutOfBounds;
+pub use assignment::{
+  AssignMovementBehaviour, Assignees, MovementBehaviourKind, MovementBehaviourWeights,
+  SetSpawnBehaviours, SpawnBehaviours,
+};
+// (only the headless statistics count the pick-ups, by behaviour)
+#[cfg(not(target_arch = "wasm32"))]
+pub use assignment::AssignedMovementBehaviour;
+#[cfg(not(target_arch = "wasm32"))]
+pub use pickups::PickedUp;
+pub use target_finding::{Target, TargetKind};
+pub use tree_assets::{BehaviourTreeAsset, BehaviourTreeSource, LeafRegistry};
+pub use walking::WalkInDirectionUntilOutOfBounds;
 
-use crate::agent::Agent;
+use crate::schedule::BehaveTick;
 
 pub fn behaviours_plugin(app: &mut App) {
   app
-    .init_resource::<CurrentMovementBehaviour>()
-    .init_resource::<NaiveMovementEnabled>()
-    .add_plugins(BehavePlugin::default())
+    .add_plugins(BehavePlugin::new(BehaveTick))
+    // (before the behaviours, which register their leaves)
+    .add_plugins(tree_assets::tree_assets_plugin)
     .add_plugins((
       walk_left_right_naive::walk_left_right_naive_plugin,
       walking::walking_plugin,
-      walk_left_right::walk_left_right_plugin,
-      walk_clockwise::walk_clockwise_plugin,
-      move_to_closest_fruit::move_to_closest_fruit_plugin,
       hunger_based::hunger_based_plugin,
       target_finding::target_finding_plugin,
       pickups::pickups_plugin,
     ))
-    .add_systems(Update, on_agent_spawn_insert_movement_behaviour)
-    .add_observer(on_clear_naive_movement_behaviours)
-    .add_observer(on_clear_movement_behaviours);
+    // (after the behaviours, so that all leaves are known by the time its trees load)
+    .add_plugins(assignment::assignment_plugin);
 }
 
-/// Clears all Bevy Behave movement behaviours for existing and new agents
-fn on_clear_movement_behaviours(
-  _trigger: Trigger<DisableMovementBehaviours>,
-  q_agents: Query<Entity, With<Agent>>,
-  q_movement_behaviours: Query<(Entity, &Parent), (With<BehaveTree>, With<MovementBehaviour>)>,
-  mut r_current_movement_behaviour: ResMut<CurrentMovementBehaviour>,
-  mut commands: Commands,
-) {
-  r_current_movement_behaviour.0 = None;
-  for (tree, parent) in q_movement_behaviours.iter() {
-    if q_agents.contains(parent.get()) {
-      commands.entity(tree).despawn_recursive();
+/// The behaviour tree files that agents are given, and whether they are loaded yet (or replaced by
+/// their built-in tree). A file that failed to load, without a built-in tree to fall back to, is an
+/// error.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub fn behaviour_trees_loaded(world: &World) -> Result<bool, String> {
+  let r_asset_server = world.resource::<AssetServer>();
+  let r_behaviour_trees = world.resource::<Assets<BehaviourTreeAsset>>();
+  let ids = assignment::movement_behaviour_trees(world)
+    .chain([pickups::pick_up_behaviour_tree(world)])
+    .collect::<Vec<_>>();
+  let mut loaded = true;
+  for id in ids {
+    if r_behaviour_trees.contains(id.typed::<BehaviourTreeAsset>()) {
+      continue;
     }
-  }
-}
-
-/// Clears all naive movement behaviours for existing and new agents
-fn on_clear_naive_movement_behaviours(
-  _trigger: Trigger<DisableNaiveMovementBehaviours>,
-  w_walk_naive: Query<Entity, (With<WalkInDirectionUntilOutOfBounds>, With<Agent>)>,
-  mut r_naive_movement_enabled: ResMut<NaiveMovementEnabled>,
-  mut commands: Commands,
-) {
-  r_naive_movement_enabled.0 = false;
-  for e in w_walk_naive.iter() {
-    commands
-      .entity(e)
-      .remove::<WalkInDirectionUntilOutOfBounds>();
-  }
-}
-
-fn on_agent_spawn_insert_movement_behaviour(
-  q_new_agents: Query<Entity, Added<Agent>>,
-  r_current_movement_behaviour: Res<CurrentMovementBehaviour>,
-  r_naive_movement_enabled: Res<NaiveMovementEnabled>,
-  mut commands: Commands,
-) {
-  if r_naive_movement_enabled.0 {
-    for agent in q_new_agents.iter() {
-      commands
-        .entity(agent)
-        .insert(WalkInDirectionUntilOutOfBounds((-1, 0)));
-    }
-  } else if let Some((tree, name)) = &r_current_movement_behaviour.0 {
-    for agent in q_new_agents.iter() {
-      commands
-        .spawn((
-          Name::new(name.clone()),
-          BehaveTree::new(tree.clone()).with_logging(false),
-          MovementBehaviour,
-        ))
-        .set_parent(agent);
+    match r_asset_server.load_state(id) {
+      LoadState::Failed(error)
+        if !r_asset_server
+          .get_path(id)
+          .is_some_and(|path| tree_assets::has_built_in_tree(path.path())) =>
+      {
+        return Err(error.to_string());
+      }
+      _ => loaded = false,
     }
   }
+  Ok(loaded)
 }
 
-#[derive(Event)]
-pub struct DisableMovementBehaviours;
-
-#[derive(Event)]
-pub struct DisableNaiveMovementBehaviours;
-
-#[derive(Resource, Default)]
-struct NaiveMovementEnabled(pub bool);
-
+/// Marks the tree that moves an agent around (it has other trees as well).
 #[derive(Component)]
-struct MovementBehaviour;
-
-#[derive(Resource, Default)]
-struct CurrentMovementBehaviour(pub Option<(Tree<Behave>, String)>);
+pub struct MovementBehaviour;
@@ -1,32 +1,42 @@
+use bevy::asset::UntypedAssetId;
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy_behave::prelude::*;
 
 use crate::{
-  agent::Agent, coins::Coin, fruit::Fruit, grid::GridCell, hunger::Hunger, points::Points,
+  agent::Agent,
+  coins::Coin,
+  fruit::Fruit,
+  grid::GridCell,
+  hunger::Hunger,
+  points::Points,
+  schedule::{PrepareTickSet, SimulationTick, TickSet},
+  spatial_index::SpatialIndex,
 };
 
+use super::{BehaviourTreeAsset, BehaviourTreeSource, LeafRegistry, TargetKind};
+
 pub fn pickups_plugin(app: &mut App) {
   app
-    .add_systems(Update, process_pick_ups)
-    .add_systems(Update, on_agent_spawn_insert_pick_up_behaviour);
-}
-
-fn build_behaviour_tree() -> Tree<bevy_behave::Behave> {
-  behave! {
-    Behave::Forever => {
-      Behave::spawn((
-        Name::new("Eat fruit when there's fruit"),
-        PickUpStuff,
-      )),
-    }
-  }
+    .world()
+    .resource::<LeafRegistry>()
+    .register("PickUpStuff", |name| Behave::spawn_named(name, PickUpStuff));
+  app
+    .init_resource::<PickUpBehaviourTree>()
+    .add_systems(SimulationTick, process_pick_ups.in_set(TickSet::PickingUp))
+    .add_systems(
+      SimulationTick,
+      insert_pick_up_behaviours.in_set(PrepareTickSet::PickUpBehaviour),
+    );
 }
 
 fn process_pick_ups(
   b_pick_up_stuff: Query<&BehaveCtx, (With<PickUpStuff>, Without<Agent>)>,
   mut q_hunger: Query<(Option<&mut Hunger>, &mut Points, &GridCell), With<Agent>>,
-  q_fruit: Query<(Entity, &Fruit, &GridCell), Without<Agent>>,
-  q_coins: Query<(Entity, &Coin, &GridCell), Without<Agent>>,
+  q_fruit: Query<&Fruit>,
+  q_coins: Query<&Coin>,
+  r_fruit_index: Res<SpatialIndex<Fruit>>,
+  r_coin_index: Res<SpatialIndex<Coin>>,
   mut commands: Commands,
 ) {
   for ctx in b_pick_up_stuff.iter() {
@@ -35,37 +45,70 @@ fn process_pick_ups(
       continue;
     };
     if let Some(mut agent_hunger) = agent_hunger {
-      for (fruit_entity, fruit, fruit_cell) in q_fruit.iter() {
-        if fruit_cell == agent_cell {
-          commands.entity(fruit_entity).despawn_recursive();
-          agent_hunger.eat(fruit.nutritional_value);
-        }
+      for fruit_entity in r_fruit_index.at(agent_cell) {
+        let Ok(fruit) = q_fruit.get(fruit_entity) else {
+          continue;
+        };
+        commands.entity(fruit_entity).despawn_recursive();
+        agent_hunger.eat(fruit.nutritional_value);
+        commands.trigger_targets(PickedUp(TargetKind::Fruit), ctx.target_entity());
       }
     }
-    for (coin_entity, coin, coin_cell) in q_coins.iter() {
-      if coin_cell == agent_cell {
-        commands.entity(coin_entity).despawn_recursive();
-        agent_points.earn(coin.monetary_value);
-      }
+    for coin_entity in r_coin_index.at(agent_cell) {
+      let Ok(coin) = q_coins.get(coin_entity) else {
+        continue;
+      };
+      commands.entity(coin_entity).despawn_recursive();
+      agent_points.earn(coin.monetary_value);
+      commands.trigger_targets(PickedUp(TargetKind::Coins), ctx.target_entity());
     }
   }
 }
 
-fn on_agent_spawn_insert_pick_up_behaviour(
-  q_new_agents: Query<Entity, Added<Agent>>,
+/// Gives the pick up behaviour to the agents that don't have it yet (once its tree is loaded).
+fn insert_pick_up_behaviours(
+  q_agents: Query<Entity, With<Agent>>,
+  q_pick_up_behaviours: Query<&Parent, With<PickUpBehaviour>>,
+  r_pick_up_behaviour_tree: Res<PickUpBehaviourTree>,
+  r_behaviour_trees: Res<Assets<BehaviourTreeAsset>>,
   mut commands: Commands,
 ) {
-  let name = "Pick up items when on an item";
-  let tree = build_behaviour_tree();
+  let Some(behaviour) = r_behaviour_trees.get(&r_pick_up_behaviour_tree.0) else {
+    return;
+  };
+  let with_behaviour = q_pick_up_behaviours
+    .iter()
+    .map(Parent::get)
+    .collect::<HashSet<_>>();
+  for agent in q_agents.iter() {
+    if !with_behaviour.contains(&agent) {
+      commands
+        .spawn((
+          Name::new(behaviour.name.clone()),
+          BehaveTree::new(behaviour.tree.clone()).with_logging(false),
+          BehaviourTreeSource(r_pick_up_behaviour_tree.0.id()),
+          PickUpBehaviour,
+        ))
+        .set_parent(agent);
+    }
+  }
+}
 
-  for agent in q_new_agents.iter() {
-    commands
-      .spawn((
-        Name::new(name),
-        BehaveTree::new(tree.clone()).with_logging(false),
-        PickUpBehaviour,
-      ))
-      .set_parent(agent);
+/// The id of the pick up behaviour's tree, to check whether it's loaded.
+pub fn pick_up_behaviour_tree(world: &World) -> UntypedAssetId {
+  world.resource::<PickUpBehaviourTree>().0.id().untyped()
+}
+
+#[derive(Resource)]
+struct PickUpBehaviourTree(Handle<BehaviourTreeAsset>);
+
+impl FromWorld for PickUpBehaviourTree {
+  fn from_world(world: &mut World) -> Self {
+    Self(
+      world
+        .resource::<AssetServer>()
+        .load("behaviours/pick_ups.behaviour.ron"),
+    )
   }
 }
 
@@ -74,3 +117,10 @@ struct PickUpStuff;
 
 #[derive(Component)]
 struct PickUpBehaviour;
+
+/// The targeted agent picked up an item (and ate it, if it was fruit).
+#[derive(Event)]
+pub struct PickedUp(
+  // (only read by the headless runner)
+  #[cfg_attr(target_arch = "wasm32", allow(dead_code))] pub TargetKind,
+);
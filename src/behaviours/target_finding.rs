@@ -1,57 +1,137 @@
+use std::collections::BTreeSet;
+
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
-use bevy_rand::{global::GlobalEntropy, prelude::WyRand};
 use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
-  agent::Agent,
+  agent::{Agent, Facing, SelectedAgent},
+  behaviours::LeafRegistry,
   coins::Coin,
+  entropy::{RngStream, Wandering},
   fruit::Fruit,
   grid::{GridBounds, GridCell},
-  schedule::TickSet,
+  memory::{AgentMemory, FogOfWarEnabled},
+  pathfinding::{Path, find_path},
+  schedule::{SimulationTick, TickSet},
+  spatial_index::SpatialIndex,
+  terrain::StepCooldown,
+  walls::{Walkability, Walls},
 };
 
 pub fn target_finding_plugin(app: &mut App) {
+  let r_leaf_registry = app.world().resource::<LeafRegistry>();
+  r_leaf_registry.register_with("FindTarget", |find_target: FindTarget, name| {
+    Behave::spawn_named(name, find_target)
+  });
+  r_leaf_registry.register("GoToTarget", |name| Behave::spawn_named(name, GoToTarget));
   app.add_systems(
-    Update,
-    (process_find_target, process_go_to_target).in_set(TickSet),
+    SimulationTick,
+    (process_find_target, process_go_to_target)
+      .chain()
+      .in_set(TickSet::TargetFinding),
   );
 }
 
 fn process_find_target(
   b_find_target: Query<(&FindTarget, &BehaveCtx)>,
-  mut q_agents: Query<(Entity, &mut GridCell), With<Agent>>,
-  q_fruits: Query<(Entity, &GridCell), (With<Fruit>, Without<Agent>)>,
-  q_coins: Query<(Entity, &GridCell), (With<Coin>, Without<Agent>)>,
+  mut q_agents: Query<
+    (
+      Entity,
+      &mut GridCell,
+      &mut StepCooldown,
+      &Facing,
+      Option<&mut AgentMemory>,
+    ),
+    With<Agent>,
+  >,
+  r_fruit_index: Res<SpatialIndex<Fruit>>,
+  r_coin_index: Res<SpatialIndex<Coin>>,
+  walkability: Walkability,
+  r_walls: Res<Walls>,
   r_grid_bounds: Res<GridBounds>,
+  r_selected_agent: Res<SelectedAgent>,
+  // (there is no fog of war when running headless)
+  r_fog_of_war_enabled: Option<Res<FogOfWarEnabled>>,
   mut commands: Commands,
-  mut rng: GlobalEntropy<WyRand>,
+  mut rng: ResMut<RngStream<Wandering>>,
 ) {
   for (find_target, ctx) in b_find_target.iter() {
-    let Ok((agent, mut agent_cell)) = q_agents.get_mut(ctx.target_entity()) else {
+    let Ok((agent, mut agent_cell, mut cooldown, facing, memory)) =
+      q_agents.get_mut(ctx.target_entity())
+    else {
       warn!("skipping behaviour that points to entity with no GridCell");
       continue;
     };
 
-    let mut closest = None;
-    let mut closest_dist = f32::MAX;
-
-    let options: Box<dyn Iterator<Item = (Entity, &GridCell)>> = match find_target.kind {
-      TargetKind::Fruit => Box::new(q_fruits.iter()),
-      TargetKind::Coins => Box::new(q_coins.iter()),
+    let options: Box<dyn Iterator<Item = (Entity, GridCell)>> = match find_target.kind {
+      TargetKind::Fruit => {
+        Box::new(r_fruit_index.within(&agent_cell, find_target.viewing_distance, &r_grid_bounds))
+      }
+      TargetKind::Coins => {
+        Box::new(r_coin_index.within(&agent_cell, find_target.viewing_distance, &r_grid_bounds))
+      }
     };
 
-    for (e, cell) in options {
-      if find_target.can_see(&agent_cell, cell) {
-        if closest.is_some() {
-          let dist = agent_cell.distance(cell);
-          if dist < closest_dist {
-            closest_dist = dist;
-            closest = Some(e);
-          }
-        } else {
-          closest = Some(e)
+    // only the items around us are candidates, of which we pick the closest one we can see
+    let mut closest = options
+      .filter(|(_, cell)| find_target.can_see(&agent_cell, facing, cell, &r_walls, &r_grid_bounds))
+      .min_by(|(_, a), (_, b)| {
+        let (a, b) = (
+          r_grid_bounds.distance(&agent_cell, a),
+          r_grid_bounds.distance(&agent_cell, b),
+        );
+        a.total_cmp(&b)
+      })
+      .map(|(e, _)| e);
+
+    // looking around is expensive, so only agents that use their memory keep it up to date (and
+    // the selected agent, for the fog of war)
+    let shows_fog_of_war = r_fog_of_war_enabled
+      .as_ref()
+      .is_some_and(|enabled| enabled.0)
+      && r_selected_agent.0 == Some(agent);
+    if let Some(mut memory) = memory.filter(|_| find_target.use_memory || shows_fog_of_war) {
+      // (in a stable order, so that runs with the same seed remember the same way)
+      let visible_cells = r_grid_bounds
+        .cells_around(&agent_cell, find_target.viewing_distance)
+        .into_iter()
+        .filter(|cell| {
+          r_grid_bounds.contains(cell)
+            && find_target.can_see(&agent_cell, facing, cell, &r_walls, &r_grid_bounds)
+        })
+        .map(|cell| r_grid_bounds.wrap(&cell))
+        .collect::<BTreeSet<_>>();
+      if find_target.use_memory {
+        // remember what we see (and forget what is not there anymore)
+        let visible_items = visible_cells
+          .iter()
+          .flat_map(|cell| {
+            let fruits = r_fruit_index
+              .at(cell)
+              .map(|e| (e, *cell, TargetKind::Fruit));
+            let coins = r_coin_index.at(cell).map(|e| (e, *cell, TargetKind::Coins));
+            fruits.chain(coins)
+          })
+          .collect::<Vec<_>>();
+        memory.look(visible_cells, visible_items);
+
+        if closest.is_none() {
+          // nothing in sight, but maybe we remember where to find something
+          closest = memory
+            .remembered(find_target.kind)
+            .filter(|(e, cell)| match find_target.kind {
+              TargetKind::Fruit => r_fruit_index.at(cell).any(|item| item == *e),
+              TargetKind::Coins => r_coin_index.at(cell).any(|item| item == *e),
+            })
+            .min_by_key(|(_, cell)| r_grid_bounds.steps(&agent_cell, cell))
+            .map(|(e, _)| e);
         }
+      } else {
+        // (only the cells, so that what the agent recalls later doesn't depend on whether it was
+        // selected)
+        memory.see(visible_cells);
       }
     }
 
@@ -59,31 +139,49 @@ fn process_find_target(
     if let Some(e) = closest {
       commands.entity(agent).insert(Target(e));
       commands.trigger(ctx.success());
-    } else {
+    } else if cooldown.tick() {
       // wander randomly
 
-      let options = agent_cell
-        .neighbours()
+      let options = r_grid_bounds
+        .neighbours(&agent_cell)
         .into_iter()
-        .filter(|c| r_grid_bounds.contains(c))
+        .filter(|c| walkability.can_step(&agent_cell, c))
         .collect::<Vec<GridCell>>();
 
+      if options.is_empty() {
+        // we're boxed in, nowhere to wander to
+        continue;
+      }
+
       let index = rng.gen_range(0..options.len());
       let target = options[index];
 
       *agent_cell = target;
+      cooldown.start(walkability.terrain(&agent_cell));
     }
   }
 }
 
 fn process_go_to_target(
   b_go_to_nearest: Query<&BehaveCtx, With<GoToTarget>>,
-  mut q_agents: Query<(Entity, &mut GridCell, &Target), With<Agent>>,
+  mut q_agents: Query<
+    (
+      Entity,
+      &mut GridCell,
+      &mut StepCooldown,
+      &Target,
+      Option<&mut Path>,
+    ),
+    With<Agent>,
+  >,
   q_targets: Query<&GridCell, Without<Agent>>,
+  walkability: Walkability,
   mut commands: Commands,
 ) {
   for ctx in b_go_to_nearest.iter() {
-    let Ok((agent, mut agent_cell, target_fruit)) = q_agents.get_mut(ctx.target_entity()) else {
+    let Ok((agent, mut agent_cell, mut cooldown, target_fruit, path)) =
+      q_agents.get_mut(ctx.target_entity())
+    else {
       warn!("skipping behaviour that points to entity with no GridCell");
       continue;
     };
@@ -92,49 +190,102 @@ fn process_go_to_target(
 
     let Ok(fruit_cell) = fruit_cell else {
       // fruit must've disappeared (or eaten by us)
-      commands.entity(agent).remove::<Target>();
+      commands.entity(agent).remove::<(Target, Path)>();
       commands.trigger(ctx.success());
       continue;
     };
 
     if *fruit_cell == *agent_cell {
       // we're sitting on the fruit, we have made it!
-      commands.entity(agent).remove::<Target>();
+      commands.entity(agent).remove::<(Target, Path)>();
       commands.trigger(ctx.success());
-    } else {
-      // we're not quite there yet, take a step in the right direction
-      agent_cell.step_to(fruit_cell);
+      continue;
+    }
+
+    if !cooldown.tick() {
+      // still wading through the current cell
+      continue;
     }
+
+    // we're not quite there yet, take a step along the path (computing a new one if needed)
+    let next_step = match path {
+      Some(mut path) if path.is_valid_for(&agent_cell, fruit_cell, &walkability) => path.advance(),
+      _ => match find_path(&agent_cell, fruit_cell, &walkability) {
+        Some(mut path) => {
+          let next_step = path.advance();
+          commands.entity(agent).insert(path);
+          next_step
+        }
+        None => None,
+      },
+    };
+
+    let Some(next_step) = next_step else {
+      // there is no way to reach the target from here
+      commands.entity(agent).remove::<(Target, Path)>();
+      commands.trigger(ctx.failure());
+      continue;
+    };
+    *agent_cell = next_step;
+    cooldown.start(walkability.terrain(&agent_cell));
   }
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Deserialize)]
 pub struct FindTarget {
   kind: TargetKind,
   viewing_distance: usize,
+  /// Whether to go for remembered items when there are none in sight.
+  #[serde(default)]
+  use_memory: bool,
+  /// The angle (in degrees) of the cone in front of the agent that it can see, if its view is
+  /// limited to the direction it's facing.
+  #[serde(default)]
+  field_of_view: Option<f32>,
 }
 
 impl FindTarget {
-  pub fn new(kind: TargetKind, viewing_distance: usize) -> Self {
-    Self {
-      kind,
-      viewing_distance,
+  /// Whether an agent at `from` can see the cell `to`. In wrap mode, `to` should be given as seen
+  /// from `from`, i.e. not wrapped onto the grid.
+  pub fn can_see(
+    &self,
+    from: &GridCell,
+    facing: &Facing,
+    to: &GridCell,
+    r_walls: &Walls,
+    r_grid_bounds: &GridBounds,
+  ) -> bool {
+    if r_grid_bounds.distance(from, to) > self.viewing_distance as f32 {
+      return false;
+    }
+
+    if let Some(field_of_view) = self.field_of_view {
+      let direction = r_grid_bounds.displacement(from, to);
+      if direction != Vec2::ZERO
+        && facing.direction().angle_to(direction).abs().to_degrees() > field_of_view / 2.0
+      {
+        return false;
+      }
     }
-  }
 
-  pub fn can_see(&self, from: &GridCell, to: &GridCell) -> bool {
-    from.distance(to) <= self.viewing_distance as f32
+    // we can't look through walls (but we can see the cells right in front of us)
+    let line = r_grid_bounds.line(from, to);
+    line.len() <= 2
+      || !line[1..line.len() - 1]
+        .iter()
+        .any(|c| r_walls.contains(&r_grid_bounds.wrap(c)))
   }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
 pub enum TargetKind {
   Fruit,
   Coins,
 }
 
+/// The item an agent is going for.
 #[derive(Component)]
-struct Target(pub Entity);
+pub struct Target(pub Entity);
 
 #[derive(Component, Clone)]
 pub struct GoToTarget;
@@ -0,0 +1,652 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_behave::prelude::*;
+use serde::de::{
+  self, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Deserializer};
+
+/// Loads behaviour trees from `.behaviour.ron` and `.behaviour.json` files, e.g.
+///
+/// ```ron
+/// (
+///   name: "Move to closest fruit",
+///   root: Forever([
+///     Sequence([
+///       Named("Find fruit", FindTarget(kind: Fruit, viewing_distance: 8)),
+///       GoToTarget,
+///     ]),
+///   ]),
+/// )
+/// ```
+///
+/// The control flow nodes are the ones of `bevy_behave` (`Forever`, `Sequence`, `Fallback`,
+/// `While`, `IfThen`, `Invert`, `Wait`, `AlwaysSucceed` and `AlwaysFail`). Everything else is a
+/// leaf, looked up by name in the [`LeafRegistry`].
+///
+/// The trees in `assets/behaviours` are also built into the app (see `BUILT_IN_TREES`), and are
+/// used when their file can't be loaded, e.g. when the page doesn't serve the `assets` directory.
+pub fn tree_assets_plugin(app: &mut App) {
+  app
+    .init_resource::<LeafRegistry>()
+    .init_asset::<BehaviourTreeAsset>()
+    .init_asset_loader::<BehaviourTreeLoader>()
+    .add_systems(Update, (fall_back_to_built_in_trees, remove_reloaded_trees));
+}
+
+/// The trees that the app ships with, by the path they are loaded from. A file at that path
+/// overrides the built-in tree.
+const BUILT_IN_TREES: [(&str, &str); 5] = [
+  (
+    "behaviours/hunger_based.behaviour.ron",
+    include_str!("../../assets/behaviours/hunger_based.behaviour.ron"),
+  ),
+  (
+    "behaviours/move_to_closest_fruit.behaviour.ron",
+    include_str!("../../assets/behaviours/move_to_closest_fruit.behaviour.ron"),
+  ),
+  (
+    "behaviours/pick_ups.behaviour.ron",
+    include_str!("../../assets/behaviours/pick_ups.behaviour.ron"),
+  ),
+  (
+    "behaviours/walk_clockwise.behaviour.ron",
+    include_str!("../../assets/behaviours/walk_clockwise.behaviour.ron"),
+  ),
+  (
+    "behaviours/walk_left_right.behaviour.ron",
+    include_str!("../../assets/behaviours/walk_left_right.behaviour.ron"),
+  ),
+];
+
+/// Whether there is a tree to fall back to if the file at `path` can't be loaded.
+pub fn has_built_in_tree(path: &Path) -> bool {
+  built_in_tree(path).is_some()
+}
+
+fn built_in_tree(path: &Path) -> Option<&'static str> {
+  BUILT_IN_TREES
+    .iter()
+    .find(|(built_in_path, _)| Path::new(built_in_path) == path)
+    .map(|(_, source)| *source)
+}
+
+/// Puts the built-in tree in place of a file that failed to load (under the same handle, so that
+/// everything that loaded the file gets the built-in tree).
+fn fall_back_to_built_in_trees(
+  mut failed_events: EventReader<AssetLoadFailedEvent<BehaviourTreeAsset>>,
+  r_leaf_registry: Res<LeafRegistry>,
+  mut r_behaviour_trees: ResMut<Assets<BehaviourTreeAsset>>,
+) {
+  for event in failed_events.read() {
+    // (a file that fails to reload keeps its previous version)
+    if r_behaviour_trees.contains(event.id) {
+      error!(
+        "could not reload behaviour tree {}: {}",
+        event.path, event.error
+      );
+      continue;
+    }
+    let Some(source) = built_in_tree(event.path.path()) else {
+      error!(
+        "could not load behaviour tree {}: {}",
+        event.path, event.error
+      );
+      continue;
+    };
+    warn!(
+      "could not load behaviour tree {} ({}), using the built-in one",
+      event.path, event.error
+    );
+    let tree = parse_tree(source.as_bytes(), false, &r_leaf_registry)
+      .expect("the built-in behaviour trees are valid");
+    r_behaviour_trees.insert(event.id, tree);
+  }
+}
+
+/// Despawns the trees whose file was edited (natively, the asset files are watched). The agents
+/// keep everything else, e.g. their hunger and points, and get the new version of the tree in the
+/// next tick, like agents that were just spawned.
+fn remove_reloaded_trees(
+  mut tree_events: EventReader<AssetEvent<BehaviourTreeAsset>>,
+  q_trees: Query<(Entity, &BehaviourTreeSource)>,
+  r_behaviour_trees: Res<Assets<BehaviourTreeAsset>>,
+  mut commands: Commands,
+) {
+  for event in tree_events.read() {
+    let AssetEvent::Modified { id } = event else {
+      continue;
+    };
+    if let Some(behaviour) = r_behaviour_trees.get(*id) {
+      info!("reloaded behaviour tree {}", behaviour.name);
+    }
+    for (tree, source) in q_trees.iter() {
+      if source.0 == *id {
+        commands.entity(tree).despawn_recursive();
+      }
+    }
+  }
+}
+
+#[derive(Asset, TypePath)]
+pub struct BehaviourTreeAsset {
+  pub name: String,
+  pub tree: Tree<Behave>,
+}
+
+/// The file that a `BehaveTree` was built from, so that it can be replaced when the file changes.
+#[derive(Component)]
+pub struct BehaviourTreeSource(pub AssetId<BehaviourTreeAsset>);
+
+/// The leaves that behaviour tree files can use, by name. Behaviours register their own leaves
+/// when they are added to the app.
+#[derive(Resource, Clone, Default)]
+pub struct LeafRegistry(Arc<RwLock<HashMap<&'static str, Leaf>>>);
+
+impl LeafRegistry {
+  /// Registers a leaf without parameters, written as e.g. `GoToTarget`.
+  pub fn register(
+    &self,
+    name: &'static str,
+    leaf: impl Fn(Cow<'static, str>) -> Behave + Send + Sync + 'static,
+  ) {
+    self.insert(name, Leaf::Unit(Box::new(leaf)));
+  }
+
+  /// Registers a leaf with parameters, written as e.g. `HungerCheck(threshold: 0.4)`.
+  pub fn register_with<P: DeserializeOwned>(
+    &self,
+    name: &'static str,
+    leaf: impl Fn(P, Cow<'static, str>) -> Behave + Send + Sync + 'static,
+  ) {
+    self.insert(
+      name,
+      Leaf::WithParams(Box::new(move |params, name| {
+        Ok(leaf(erased_serde::deserialize(params)?, name))
+      })),
+    );
+  }
+
+  fn insert(&self, name: &'static str, leaf: Leaf) {
+    if self.0.write().unwrap().insert(name, leaf).is_some() {
+      warn!("leaf {name} was registered twice");
+    }
+  }
+}
+
+enum Leaf {
+  Unit(Box<dyn Fn(Cow<'static, str>) -> Behave + Send + Sync>),
+  WithParams(
+    Box<
+      dyn Fn(
+          &mut dyn erased_serde::Deserializer,
+          Cow<'static, str>,
+        ) -> Result<Behave, erased_serde::Error>
+        + Send
+        + Sync,
+    >,
+  ),
+}
+
+struct BehaviourTreeLoader {
+  registry: LeafRegistry,
+}
+
+impl FromWorld for BehaviourTreeLoader {
+  fn from_world(world: &mut World) -> Self {
+    Self {
+      registry: world.resource::<LeafRegistry>().clone(),
+    }
+  }
+}
+
+impl AssetLoader for BehaviourTreeLoader {
+  type Asset = BehaviourTreeAsset;
+  type Settings = ();
+  type Error = BehaviourTreeLoaderError;
+
+  async fn load(
+    &self,
+    reader: &mut dyn Reader,
+    _settings: &(),
+    load_context: &mut LoadContext<'_>,
+  ) -> Result<BehaviourTreeAsset, BehaviourTreeLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+
+    let is_json = load_context.path().to_string_lossy().ends_with(".json");
+    parse_tree(&bytes, is_json, &self.registry)
+  }
+
+  fn extensions(&self) -> &[&str] {
+    &["behaviour.ron", "behaviour.json"]
+  }
+}
+
+/// Reads a tree from the contents of a `.behaviour.ron` (or `.behaviour.json`) file.
+fn parse_tree(
+  bytes: &[u8],
+  is_json: bool,
+  registry: &LeafRegistry,
+) -> Result<BehaviourTreeAsset, BehaviourTreeLoaderError> {
+  let registry = registry.0.read().unwrap();
+  let seed = FileSeed {
+    registry: &registry,
+  };
+  let (name, root) = if is_json {
+    seed.deserialize(&mut serde_json::Deserializer::from_slice(bytes))?
+  } else {
+    // (so that leaves can be written as `FindTarget(kind: Fruit)` rather than
+    // `FindTarget((kind: Fruit))`)
+    ron::Options::default()
+      .with_default_extension(ron::extensions::Extensions::UNWRAP_VARIANT_NEWTYPES)
+      .from_bytes_seed(bytes, seed)?
+  };
+
+  let mut tree = Tree::new(root.behave);
+  append_children(tree.root_mut(), root.children);
+  Ok(BehaviourTreeAsset { name, tree })
+}
+
+fn append_children(mut parent: NodeMut<Behave>, children: Vec<Node>) {
+  for child in children {
+    let node = parent.append(child.behave);
+    append_children(node, child.children);
+  }
+}
+
+#[derive(Debug)]
+pub enum BehaviourTreeLoaderError {
+  Io(std::io::Error),
+  Ron(ron::error::SpannedError),
+  Json(serde_json::Error),
+}
+
+impl fmt::Display for BehaviourTreeLoaderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(error) => write!(f, "could not read behaviour tree: {error}"),
+      Self::Ron(error) => write!(f, "invalid behaviour tree: {error}"),
+      Self::Json(error) => write!(f, "invalid behaviour tree: {error}"),
+    }
+  }
+}
+
+impl std::error::Error for BehaviourTreeLoaderError {}
+
+impl From<std::io::Error> for BehaviourTreeLoaderError {
+  fn from(error: std::io::Error) -> Self {
+    Self::Io(error)
+  }
+}
+
+impl From<ron::error::SpannedError> for BehaviourTreeLoaderError {
+  fn from(error: ron::error::SpannedError) -> Self {
+    Self::Ron(error)
+  }
+}
+
+impl From<serde_json::Error> for BehaviourTreeLoaderError {
+  fn from(error: serde_json::Error) -> Self {
+    Self::Json(error)
+  }
+}
+
+/// A node of a tree that is being read, before it is put in a [`Tree`].
+struct Node {
+  behave: Behave,
+  children: Vec<Node>,
+}
+
+impl Node {
+  fn leaf(behave: Behave) -> Self {
+    Self {
+      behave,
+      children: Vec::new(),
+    }
+  }
+}
+
+/// Reads a whole file: the name of the tree, and its root node.
+struct FileSeed<'a> {
+  registry: &'a HashMap<&'static str, Leaf>,
+}
+
+impl<'de> DeserializeSeed<'de> for FileSeed<'_> {
+  type Value = (String, Node);
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+    deserializer.deserialize_struct("BehaviourTree", &["name", "root"], self)
+  }
+}
+
+impl<'de> Visitor<'de> for FileSeed<'_> {
+  type Value = (String, Node);
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a behaviour tree with a name and a root")
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+    #[derive(Deserialize)]
+    #[serde(field_identifier, rename_all = "snake_case")]
+    enum Field {
+      Name,
+      Root,
+    }
+
+    let (mut name, mut root) = (None, None);
+    while let Some(field) = map.next_key()? {
+      match field {
+        Field::Name => name = Some(map.next_value()?),
+        Field::Root => {
+          root = Some(map.next_value_seed(NodeSeed {
+            registry: self.registry,
+            name: None,
+          })?)
+        }
+      }
+    }
+    Ok((
+      name.ok_or_else(|| de::Error::missing_field("name"))?,
+      root.ok_or_else(|| de::Error::missing_field("root"))?,
+    ))
+  }
+}
+
+/// Reads a node (and its children), giving it `name` if it is a leaf.
+#[derive(Clone, Copy)]
+struct NodeSeed<'a> {
+  registry: &'a HashMap<&'static str, Leaf>,
+  name: Option<&'a str>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+  type Value = Node;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+    deserializer.deserialize_enum("Node", &[], self)
+  }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_> {
+  type Value = Node;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a behaviour tree node")
+  }
+
+  fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Node, A::Error> {
+    let (NodeKind(kind), variant) = data.variant()?;
+    let composite = |behave, permitted_children| ChildrenSeed {
+      behave,
+      permitted_children,
+      registry: self.registry,
+    };
+    let node = match kind.as_str() {
+      "Forever" => variant.newtype_variant_seed(composite(Behave::Forever, 1..=1))?,
+      "Sequence" => variant.newtype_variant_seed(composite(Behave::Sequence, 0..=usize::MAX))?,
+      "Fallback" => variant.newtype_variant_seed(composite(Behave::Fallback, 0..=usize::MAX))?,
+      "While" => variant.newtype_variant_seed(composite(Behave::While, 1..=2))?,
+      "IfThen" => variant.newtype_variant_seed(composite(Behave::IfThen, 2..=3))?,
+      "Invert" => Node {
+        behave: Behave::Invert,
+        children: vec![variant.newtype_variant_seed(NodeSeed { name: None, ..self })?],
+      },
+      "Wait" => Node::leaf(Behave::Wait(variant.newtype_variant()?)),
+      "AlwaysSucceed" => {
+        variant.unit_variant()?;
+        Node::leaf(Behave::AlwaysSucceed)
+      }
+      "AlwaysFail" => {
+        variant.unit_variant()?;
+        Node::leaf(Behave::AlwaysFail)
+      }
+      // gives the leaf in it a name (leaves are named after their kind otherwise)
+      "Named" => variant.tuple_variant(
+        2,
+        NamedVisitor {
+          registry: self.registry,
+        },
+      )?,
+      _ => {
+        let Some(leaf) = self.registry.get(kind.as_str()) else {
+          return Err(de::Error::custom(format_args!(
+            "unknown behaviour tree node `{kind}`"
+          )));
+        };
+        let name = Cow::Owned(self.name.unwrap_or(&kind).to_string());
+        let behave = match leaf {
+          Leaf::Unit(leaf) => {
+            variant.unit_variant()?;
+            leaf(name)
+          }
+          Leaf::WithParams(leaf) => variant.newtype_variant_seed(LeafParamsSeed { leaf, name })?,
+        };
+        Node::leaf(behave)
+      }
+    };
+    Ok(node)
+  }
+}
+
+/// The kind of a node, which is either a control flow node or the name of a leaf.
+struct NodeKind(String);
+
+impl<'de> Deserialize<'de> for NodeKind {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct NodeKindVisitor;
+
+    impl Visitor<'_> for NodeKindVisitor {
+      type Value = NodeKind;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the kind of a behaviour tree node")
+      }
+
+      fn visit_str<E: de::Error>(self, kind: &str) -> Result<NodeKind, E> {
+        Ok(NodeKind(kind.to_string()))
+      }
+    }
+
+    deserializer.deserialize_identifier(NodeKindVisitor)
+  }
+}
+
+/// Reads the children of a control flow node.
+struct ChildrenSeed<'a> {
+  behave: Behave,
+  permitted_children: std::ops::RangeInclusive<usize>,
+  registry: &'a HashMap<&'static str, Leaf>,
+}
+
+impl<'de> DeserializeSeed<'de> for ChildrenSeed<'_> {
+  type Value = Node;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+    deserializer.deserialize_seq(self)
+  }
+}
+
+impl<'de> Visitor<'de> for ChildrenSeed<'_> {
+  type Value = Node;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a list of children for {}", self.behave)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+    let mut children = Vec::new();
+    while let Some(child) = seq.next_element_seed(NodeSeed {
+      registry: self.registry,
+      name: None,
+    })? {
+      children.push(child);
+    }
+
+    // (bevy_behave panics on trees with the wrong number of children)
+    if !self.permitted_children.contains(&children.len()) {
+      return Err(de::Error::custom(format_args!(
+        "{} can't have {} children",
+        self.behave,
+        children.len()
+      )));
+    }
+    Ok(Node {
+      behave: self.behave,
+      children,
+    })
+  }
+}
+
+/// Reads `Named("name", node)`.
+struct NamedVisitor<'a> {
+  registry: &'a HashMap<&'static str, Leaf>,
+}
+
+impl<'de> Visitor<'de> for NamedVisitor<'_> {
+  type Value = Node;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a name and a behaviour tree node")
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+    let name: String = seq
+      .next_element()?
+      .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+    seq
+      .next_element_seed(NodeSeed {
+        registry: self.registry,
+        name: Some(&name),
+      })?
+      .ok_or_else(|| de::Error::invalid_length(1, &self))
+  }
+}
+
+/// Reads the parameters of a leaf, and builds the leaf from them.
+struct LeafParamsSeed<'a> {
+  leaf: &'a (
+        dyn Fn(
+    &mut dyn erased_serde::Deserializer,
+    Cow<'static, str>,
+  ) -> Result<Behave, erased_serde::Error>
+          + Send
+          + Sync
+      ),
+  name: Cow<'static, str>,
+}
+
+impl<'de> DeserializeSeed<'de> for LeafParamsSeed<'_> {
+  type Value = Behave;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Behave, D::Error> {
+    let mut deserializer = <dyn erased_serde::Deserializer>::erase(deserializer);
+    (self.leaf)(&mut deserializer, self.name).map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Deserialize)]
+  struct HungerCheck {
+    threshold: f32,
+  }
+
+  fn registry() -> LeafRegistry {
+    let registry = LeafRegistry::default();
+    registry.register("GoToTarget", |name| Behave::spawn_named(name, ()));
+    // (a stand-in for the real leaf: all that matters is that its parameters are read)
+    registry.register_with("HungerCheck", |params: HungerCheck, _name| {
+      Behave::Wait(params.threshold)
+    });
+    registry
+  }
+
+  /// The error for a tree that must not load.
+  fn load_error(source: &str, is_json: bool) -> String {
+    match parse_tree(source.as_bytes(), is_json, &registry()) {
+      Ok(tree) => panic!("{source} loaded as {}", tree.name),
+      Err(error) => error.to_string(),
+    }
+  }
+
+  #[test]
+  fn parses_ron_and_json() {
+    let ron = r#"(name: "Hungry", root: Forever([
+      Sequence([HungerCheck(threshold: 0.4), Named("Go", GoToTarget)]),
+    ]))"#;
+    let json = r#"{"name": "Hungry", "root": {"Forever": [
+      {"Sequence": [{"HungerCheck": {"threshold": 0.4}}, {"Named": ["Go", "GoToTarget"]}]}
+    ]}}"#;
+    for (source, is_json) in [(ron, false), (json, true)] {
+      let tree = parse_tree(source.as_bytes(), is_json, &registry()).unwrap();
+      assert_eq!(tree.name, "Hungry");
+      assert_eq!(tree.tree.nodes().count(), 4);
+    }
+  }
+
+  #[test]
+  fn rejects_wrong_child_counts() {
+    for root in [
+      "Forever([GoToTarget, GoToTarget])",
+      "Forever([])",
+      "While([])",
+      "IfThen([GoToTarget])",
+      "IfThen([GoToTarget, GoToTarget, GoToTarget, GoToTarget])",
+    ] {
+      let error = load_error(&format!(r#"(name: "Bad", root: {root})"#), false);
+      assert!(error.contains("can't have"), "{root}: {error}");
+    }
+    let error = load_error(
+      r#"{"name": "Bad", "root": {"Forever": ["GoToTarget", "GoToTarget"]}}"#,
+      true,
+    );
+    assert!(error.contains("can't have 2 children"), "{error}");
+  }
+
+  #[test]
+  fn rejects_unknown_leaves() {
+    let error = load_error(r#"(name: "Bad", root: Forever([Teleport]))"#, false);
+    assert!(
+      error.contains("unknown behaviour tree node `Teleport`"),
+      "{error}"
+    );
+    let error = load_error(
+      r#"{"name": "Bad", "root": {"Forever": ["Teleport"]}}"#,
+      true,
+    );
+    assert!(
+      error.contains("unknown behaviour tree node `Teleport`"),
+      "{error}"
+    );
+  }
+
+  #[test]
+  fn rejects_bad_leaf_parameters() {
+    for root in [
+      r#"HungerCheck(threshold: "high")"#,
+      "HungerCheck(limit: 0.4)",
+      "HungerCheck",
+    ] {
+      let error = load_error(&format!(r#"(name: "Bad", root: {root})"#), false);
+      assert!(
+        error.starts_with("invalid behaviour tree"),
+        "{root}: {error}"
+      );
+    }
+    let error = load_error(
+      r#"{"name": "Bad", "root": {"HungerCheck": {"threshold": "high"}}}"#,
+      true,
+    );
+    assert!(error.contains("invalid type"), "{error}");
+  }
+}
@@ -2,47 +2,52 @@ use bevy::prelude::*;
 
 use crate::{
   agent::Agent,
-  grid::{GridBounds, GridCell},
-  schedule::TickSet,
+  grid::GridCell,
+  schedule::{SimulationTick, TickSet},
+  terrain::StepCooldown,
+  walls::Walkability,
 };
 
-use super::{NaiveMovementEnabled, walking::WalkInDirectionUntilOutOfBounds};
+use super::walking::WalkInDirectionUntilOutOfBounds;
 
 pub fn walk_left_right_naive_plugin(app: &mut App) {
-  app
-    .add_observer(enable_behaviour)
-    .add_systems(Update, process_left_right_walk.in_set(TickSet));
-}
-
-fn enable_behaviour(
-  _trigger: Trigger<SetBehaviourWalkLeftRightNaive>,
-  q_agents: Query<Entity, With<Agent>>,
-  mut r_naive_movement_enabled: ResMut<NaiveMovementEnabled>,
-  mut commands: Commands,
-) {
-  r_naive_movement_enabled.0 = true;
-
-  for agent in q_agents.iter() {
-    commands
-      .entity(agent)
-      .insert(WalkInDirectionUntilOutOfBounds::new(-1, 0));
-  }
+  app.add_systems(
+    SimulationTick,
+    process_left_right_walk.in_set(TickSet::NaiveWalking),
+  );
 }
 
 fn process_left_right_walk(
-  mut q_walkers: Query<(&mut GridCell, &mut WalkInDirectionUntilOutOfBounds), With<Agent>>,
-  r_bounds: Res<GridBounds>,
+  mut q_walkers: Query<
+    (
+      &mut GridCell,
+      &mut StepCooldown,
+      &mut WalkInDirectionUntilOutOfBounds,
+    ),
+    With<Agent>,
+  >,
+  walkability: Walkability,
 ) {
   // loop over all grid cells & walk components that
   // are attached to agents
-  for (mut grid_cell, mut walk) in q_walkers.iter_mut() {
+  for (mut grid_cell, mut cooldown, mut walk) in q_walkers.iter_mut() {
+    // terrain like mud takes a couple of ticks to get through
+    if !cooldown.tick() {
+      continue;
+    }
+
     // determine the next step, and update the agent's
-    // grid cell (make it move there)
-    *grid_cell = walk.step_from(&grid_cell);
+    // grid cell (make it move there), unless it's blocked
+    let target = walk.step_from(&grid_cell, walkability.grid_bounds());
+    if walkability.is_walkable(&target) {
+      *grid_cell = target;
+      cooldown.start(walkability.terrain(&grid_cell));
+    }
 
     // let's see if the next step will put us out of bounds
-    let next_target = walk.step_from(&grid_cell);
-    if !r_bounds.contains(&next_target) {
+    // or into a wall
+    let next_target = walk.step_from(&grid_cell, walkability.grid_bounds());
+    if !walkability.is_walkable(&next_target) {
       // the next step would've put the agent out of bounds,
       // so we reverse (basically just flip -1 to +1 and
       // vice versa)
@@ -50,6 +55,3 @@ fn process_left_right_walk(
     }
   }
 }
-
-#[derive(Event)]
-pub struct SetBehaviourWalkLeftRightNaive;
@@ -1,51 +1,87 @@
 use crate::{
   agent::Agent,
+  behaviours::LeafRegistry,
   grid::{GridBounds, GridCell},
-  schedule::TickSet,
+  schedule::{SimulationTick, TickSet},
+  terrain::StepCooldown,
+  walls::Walkability,
 };
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
+use serde::Deserialize;
 
 pub fn walking_plugin(app: &mut App) {
-  app.add_systems(Update, process_walk_in_direction.in_set(TickSet));
+  app.world().resource::<LeafRegistry>().register_with(
+    "WalkInDirectionUntilOutOfBounds",
+    |walk: WalkInDirectionUntilOutOfBounds, name| Behave::spawn_named(name, walk),
+  );
+  app.add_systems(
+    SimulationTick,
+    process_walk_in_direction.in_set(TickSet::Walking),
+  );
 }
 
-#[derive(Component, Clone)]
-pub struct WalkInDirectionUntilOutOfBounds(pub (isize, isize));
+/// Walks in a direction until the edge (or a wall) is reached. In wrap mode there is no edge, so
+/// the walk is complete once the agent has gone all the way around and is back where it started.
+#[derive(Component, Clone, Deserialize)]
+pub struct WalkInDirectionUntilOutOfBounds {
+  direction: (isize, isize),
+  #[serde(skip)]
+  start: Option<GridCell>,
+}
 
 impl WalkInDirectionUntilOutOfBounds {
   pub fn new(x: isize, y: isize) -> Self {
-    Self((x, y))
+    Self {
+      direction: (x, y),
+      start: None,
+    }
   }
 
   pub fn reverse(&mut self) {
-    self.0.0 = -self.0.0;
-    self.0.1 = -self.0.1;
+    self.direction.0 = -self.direction.0;
+    self.direction.1 = -self.direction.1;
   }
 
-  pub fn step_from(&self, from: &GridCell) -> GridCell {
-    GridCell::new(from.x + self.0.0, from.y + self.0.1)
+  pub fn step_from(&self, from: &GridCell, r_grid_bounds: &GridBounds) -> GridCell {
+    r_grid_bounds.step(from, self.direction)
   }
 }
 
 fn process_walk_in_direction(
-  q_walks: Query<(&WalkInDirectionUntilOutOfBounds, &BehaveCtx)>,
-  mut q_agent_cells: Query<&mut GridCell, With<Agent>>,
-  r_bounds: Res<GridBounds>,
+  mut q_walks: Query<(&mut WalkInDirectionUntilOutOfBounds, &BehaveCtx)>,
+  mut q_agent_cells: Query<(&mut GridCell, &mut StepCooldown), With<Agent>>,
+  walkability: Walkability,
   mut commands: Commands,
 ) {
-  for (walk, ctx) in q_walks.iter() {
-    let Ok(mut agent_cell) = q_agent_cells.get_mut(ctx.target_entity()) else {
+  for (mut walk, ctx) in q_walks.iter_mut() {
+    let Ok((mut agent_cell, mut cooldown)) = q_agent_cells.get_mut(ctx.target_entity()) else {
       warn!("skipping behaviour that points to entity with no GridCell");
       continue;
     };
 
-    let target = walk.step_from(&agent_cell);
+    if !cooldown.tick() {
+      // still wading through the current cell
+      continue;
+    }
+
+    let start = *walk.start.get_or_insert(*agent_cell);
+
+    let target = walk.step_from(&agent_cell, walkability.grid_bounds());
+    if !walkability.is_walkable(&target) {
+      // we're already up against a wall (or the edge), so there's nothing left to walk
+      commands.trigger(ctx.success());
+      continue;
+    }
     *agent_cell = target;
+    cooldown.start(walkability.terrain(&agent_cell));
 
-    let next_target = walk.step_from(&agent_cell);
-    if !r_bounds.contains(&next_target) {
-      // the next step would've put the agent out of bounds, so we successfully completed the behaviour step
+    let next_target = walk.step_from(&agent_cell, walkability.grid_bounds());
+    if !walkability.is_walkable(&next_target) {
+      // the next step would've put the agent out of bounds or into a wall, so we successfully completed the behaviour step
+      commands.trigger(ctx.success());
+    } else if *agent_cell == start {
+      // we went all the way around the world (in wrap mode), which is as far as we can walk
       commands.trigger(ctx.success());
     }
   }
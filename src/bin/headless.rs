@@ -0,0 +1,26 @@
+use behave_blog_demo::headless::{self, HeadlessConfig};
+
+fn main() {
+  let Some(path) = std::env::args().nth(1) else {
+    eprintln!("usage: headless <config.json>");
+    std::process::exit(2);
+  };
+  let config = match std::fs::read_to_string(&path) {
+    Ok(config) => config,
+    Err(error) => {
+      eprintln!("could not read {path}: {error}");
+      std::process::exit(1);
+    }
+  };
+  let config: HeadlessConfig = match serde_json::from_str(&config) {
+    Ok(config) => config,
+    Err(error) => {
+      eprintln!("invalid config {path}: {error}");
+      std::process::exit(1);
+    }
+  };
+  if let Err(error) = headless::run(&config) {
+    eprintln!("{error}");
+    std::process::exit(1);
+  }
+}
@@ -0,0 +1,180 @@
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::input::touch::Touches;
+use bevy::prelude::*;
+
+use crate::{
+  agent::{Agent, SelectedAgent},
+  grid::{CellSize, GridBounds},
+  resizing::CellSizeChanged,
+};
+
+pub fn camera_plugin(app: &mut App) {
+  app
+    .init_resource::<CameraFollow>()
+    .add_systems(Startup, spawn_camera)
+    .add_systems(
+      Update,
+      (pan_camera, zoom_camera, follow_selected_agent).chain(),
+    )
+    .add_observer(toggle_camera_follow)
+    .add_observer(fit_camera_to_world)
+    .add_observer(fit_camera_on_first_layout);
+}
+
+fn spawn_camera(mut commands: Commands) {
+  commands.spawn(Camera2d);
+}
+
+/// Moves the camera along when dragging the mouse (or a single finger) over the canvas.
+fn pan_camera(
+  r_mouse_buttons: Res<ButtonInput<MouseButton>>,
+  r_mouse_motion: Res<AccumulatedMouseMotion>,
+  r_touches: Res<Touches>,
+  q_camera: Single<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+  mut r_camera_follow: ResMut<CameraFollow>,
+) {
+  let (mut transform, projection) = q_camera.into_inner();
+
+  let mut drag = Vec2::ZERO;
+  if r_mouse_buttons.pressed(MouseButton::Left) {
+    drag += r_mouse_motion.delta;
+  }
+  let touches = r_touches.iter().collect::<Vec<_>>();
+  if let [touch] = touches[..] {
+    drag += touch.delta();
+  }
+  if drag == Vec2::ZERO {
+    return;
+  }
+
+  // screen coordinates point down, world coordinates point up
+  transform.translation.x -= drag.x * projection.scale;
+  transform.translation.y += drag.y * projection.scale;
+
+  // we're looking somewhere else now
+  r_camera_follow.set_if_neq(CameraFollow(false));
+}
+
+/// Zooms in and out with the mouse wheel (around the cursor) or by pinching with two fingers
+/// (around the center of the screen).
+fn zoom_camera(
+  r_mouse_scroll: Res<AccumulatedMouseScroll>,
+  r_touches: Res<Touches>,
+  q_window: Single<&Window>,
+  q_camera: Single<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+  let (mut transform, mut projection) = q_camera.into_inner();
+
+  let mut zoom = match r_mouse_scroll.unit {
+    MouseScrollUnit::Line => (-r_mouse_scroll.delta.y * ZOOM_PER_LINE).exp(),
+    MouseScrollUnit::Pixel => (-r_mouse_scroll.delta.y * ZOOM_PER_PIXEL).exp(),
+  };
+  let touches = r_touches.iter().collect::<Vec<_>>();
+  if let [a, b] = touches[..] {
+    let distance = a.position().distance(b.position());
+    let previous_distance = a.previous_position().distance(b.previous_position());
+    if distance > 0.0 && previous_distance > 0.0 {
+      zoom *= previous_distance / distance;
+    }
+  }
+  if zoom == 1.0 {
+    return;
+  }
+
+  let scale = (projection.scale * zoom).clamp(MIN_CAMERA_SCALE, MAX_CAMERA_SCALE);
+
+  // keep the point under the cursor in place
+  if let Some(cursor) = q_window.cursor_position() {
+    let from_center = (cursor - q_window.size() / 2.0) * Vec2::new(1.0, -1.0);
+    transform.translation += (from_center * (projection.scale - scale)).extend(0.0);
+  }
+  projection.scale = scale;
+}
+
+fn follow_selected_agent(
+  r_camera_follow: Res<CameraFollow>,
+  r_selected_agent: Res<SelectedAgent>,
+  q_agents: Query<&Transform, (With<Agent>, Without<Camera2d>)>,
+  mut q_camera: Single<&mut Transform, With<Camera2d>>,
+  r_time: Res<Time>,
+) {
+  if !r_camera_follow.0 {
+    return;
+  }
+  let Some(agent) = r_selected_agent
+    .0
+    .and_then(|agent| q_agents.get(agent).ok())
+  else {
+    return;
+  };
+
+  // ease towards the agent, rather than jumping along with every step
+  let target = agent.translation.truncate();
+  let current = q_camera.translation.truncate();
+  let t = 1.0 - (-FOLLOW_SPEED * r_time.delta_secs()).exp();
+  q_camera.translation = current.lerp(target, t).extend(q_camera.translation.z);
+}
+
+fn toggle_camera_follow(
+  _trigger: Trigger<ToggleCameraFollow>,
+  mut r_camera_follow: ResMut<CameraFollow>,
+) {
+  r_camera_follow.0 = !r_camera_follow.0;
+}
+
+/// Centers the camera on the world, zoomed out such that all of it is in view.
+fn fit_camera_to_world(
+  _trigger: Trigger<FitCameraToWorld>,
+  q_window: Single<&Window>,
+  q_camera: Single<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+  r_grid_bounds: Res<GridBounds>,
+  r_cell_size: Res<CellSize>,
+) {
+  let (mut transform, mut projection) = q_camera.into_inner();
+
+  let world_size = r_grid_bounds.world_size(r_cell_size.0);
+  let window_size = q_window.size();
+  if window_size.min_element() <= 0.0 {
+    return;
+  }
+
+  transform.translation.x = 0.0;
+  transform.translation.y = 0.0;
+  // (worlds that fit in the window already are shown at their actual size)
+  projection.scale = (world_size / window_size)
+    .max_element()
+    .clamp(1.0, MAX_CAMERA_SCALE);
+}
+
+/// Fits the world in view once it is first laid out. After that, the camera stays where the user
+/// put it (also when the window or the grid is resized), until they ask for it to be fitted again.
+fn fit_camera_on_first_layout(
+  _trigger: Trigger<CellSizeChanged>,
+  q_window: Single<&Window>,
+  mut fitted: Local<bool>,
+  mut commands: Commands,
+) {
+  // (the window may not have a size yet)
+  if *fitted || q_window.size().min_element() <= 0.0 {
+    return;
+  }
+  *fitted = true;
+  commands.trigger(FitCameraToWorld);
+}
+
+const ZOOM_PER_LINE: f32 = 0.1;
+const ZOOM_PER_PIXEL: f32 = 0.002;
+const MIN_CAMERA_SCALE: f32 = 0.05;
+const MAX_CAMERA_SCALE: f32 = 100.0;
+/// How quickly the camera catches up with the agent it follows (higher is faster).
+const FOLLOW_SPEED: f32 = 5.0;
+
+/// Whether the camera follows the selected agent around.
+#[derive(Resource, Default, PartialEq)]
+pub struct CameraFollow(pub bool);
+
+#[derive(Event)]
+pub struct ToggleCameraFollow;
+
+#[derive(Event)]
+pub struct FitCameraToWorld;
@@ -1,16 +1,19 @@
-use bevy::color::palettes::tailwind as tw;
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
-use bevy_rand::prelude::{GlobalEntropy, WyRand};
 
 use crate::{
-  grid::{GridBounds, GridCell},
-  schedule::TickSet,
+  entropy::{CoinSpawning, RngStream},
+  grid::GridCell,
+  schedule::{SimulationTick, TickSet},
+  walls::Walkability,
 };
 
 pub fn coins_plugin(app: &mut App) {
   app
-    .add_systems(Update, process_spawn_coins_task.in_set(TickSet))
+    .add_systems(
+      SimulationTick,
+      process_spawn_coins_task.in_set(TickSet::CoinSpawning),
+    )
     .add_observer(spawn_coins_spawner);
 }
 
@@ -47,11 +50,9 @@ fn process_spawn_coins_task(
   b_spawn_until_enough: Query<&BehaveCtx, With<SpawnCoinsUntilEnough>>,
   q_coin_spawners: Query<&CoinSpawner>,
   q_coins: Query<(), With<Coin>>,
-  r_grid_bounds: Res<GridBounds>,
-  mut r_meshes: ResMut<Assets<Mesh>>,
-  mut r_materials: ResMut<Assets<ColorMaterial>>,
+  walkability: Walkability,
   mut commands: Commands,
-  mut rng: GlobalEntropy<WyRand>,
+  mut rng: ResMut<RngStream<CoinSpawning>>,
 ) {
   let mut n_coins = None;
   for ctx in b_spawn_until_enough.iter() {
@@ -65,13 +66,11 @@ fn process_spawn_coins_task(
     }
 
     if n_coins.unwrap() < spawner.target_coin_number {
-      let cell = r_grid_bounds.get_random_position(&mut rng);
-      commands.spawn((
-        Coin::new(2),
-        cell,
-        Mesh2d(r_meshes.add(Circle::new(0.25))),
-        MeshMaterial2d(r_materials.add(Color::from(tw::YELLOW_400))),
-      ));
+      let Some(cell) = walkability.get_random_position(&mut *rng) else {
+        warn!("could not find a free cell to spawn a coin in");
+        continue;
+      };
+      commands.spawn((Coin::new(2), cell));
     }
   }
 }
@@ -0,0 +1,161 @@
+use core::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy_rand::prelude::WyRand;
+use rand_core::{RngCore, SeedableRng};
+
+pub fn entropy_plugin(app: &mut App) {
+  app
+    .init_resource::<SimulationSeed>()
+    .add_plugins((
+      rng_stream_for::<AgentSpawning>,
+      rng_stream_for::<FruitSpawning>,
+      rng_stream_for::<CoinSpawning>,
+      rng_stream_for::<Wandering>,
+      rng_stream_for::<TerrainScattering>,
+      rng_stream_for::<MapGeneration>,
+      rng_stream_for::<BehaviourAssignment>,
+    ))
+    .add_observer(set_seed);
+}
+
+/// Keeps an [`RngStream`] for `T` that is reseeded whenever the [`SimulationSeed`] changes.
+fn rng_stream_for<T: Subsystem>(app: &mut App) {
+  app.init_resource::<RngStream<T>>().add_systems(
+    First,
+    reseed_rng_stream::<T>.run_if(resource_changed::<SimulationSeed>),
+  );
+}
+
+fn reseed_rng_stream<T: Subsystem>(
+  r_seed: Res<SimulationSeed>,
+  mut r_stream: ResMut<RngStream<T>>,
+) {
+  *r_stream = RngStream::new(r_seed.0);
+}
+
+fn set_seed(trigger: Trigger<SetSeed>, mut r_seed: ResMut<SimulationSeed>) {
+  r_seed.0 = trigger.event().0;
+  info!("seed: {}", r_seed.0);
+}
+
+/// The seed that all randomness in the simulation derives from. Unless the page picks one, it is
+/// random.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationSeed(pub u64);
+
+impl SimulationSeed {
+  /// Reads the seed from a URL query string, e.g. `?seed=1234`.
+  pub fn from_query(query: &str) -> Option<Self> {
+    query
+      .trim_start_matches('?')
+      .split('&')
+      .filter_map(|pair| pair.split_once('='))
+      .find(|(key, _)| *key == "seed")
+      .and_then(|(_, value)| value.parse().ok())
+      .map(Self)
+  }
+}
+
+impl Default for SimulationSeed {
+  fn default() -> Self {
+    Self(rand::random())
+  }
+}
+
+/// A random number generator for a single subsystem. Every stream is seeded from the
+/// [`SimulationSeed`] and the [`Subsystem::NAME`] of its subsystem, so a subsystem drawing more
+/// (or fewer) numbers doesn't change what the others draw.
+#[derive(Resource)]
+pub struct RngStream<T> {
+  rng: WyRand,
+  _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Subsystem> RngStream<T> {
+  fn new(seed: u64) -> Self {
+    Self {
+      rng: WyRand::seed_from_u64(seed ^ fnv1a(T::NAME)),
+      _marker: PhantomData,
+    }
+  }
+}
+
+impl<T: Subsystem> Default for RngStream<T> {
+  fn default() -> Self {
+    Self::new(0)
+  }
+}
+
+impl<T> RngCore for RngStream<T> {
+  fn next_u32(&mut self) -> u32 {
+    self.rng.next_u32()
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.rng.next_u64()
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    self.rng.fill_bytes(dest)
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+    self.rng.try_fill_bytes(dest)
+  }
+}
+
+/// (a stable hash, unlike the ones in std)
+fn fnv1a(name: &str) -> u64 {
+  name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+  })
+}
+
+/// A part of the simulation that has its own [`RngStream`].
+pub trait Subsystem: Send + Sync + 'static {
+  /// What the stream is seeded with (besides the [`SimulationSeed`]). Changing it changes what
+  /// every seed plays out like, so it should stay the same once it's picked.
+  const NAME: &'static str;
+}
+
+pub struct AgentSpawning;
+impl Subsystem for AgentSpawning {
+  const NAME: &'static str = "agent spawning";
+}
+
+pub struct FruitSpawning;
+impl Subsystem for FruitSpawning {
+  const NAME: &'static str = "fruit spawning";
+}
+
+pub struct CoinSpawning;
+impl Subsystem for CoinSpawning {
+  const NAME: &'static str = "coin spawning";
+}
+
+/// Agents picking a random direction when there is nothing to go to.
+pub struct Wandering;
+impl Subsystem for Wandering {
+  const NAME: &'static str = "wandering";
+}
+
+pub struct TerrainScattering;
+impl Subsystem for TerrainScattering {
+  const NAME: &'static str = "terrain scattering";
+}
+
+pub struct MapGeneration;
+impl Subsystem for MapGeneration {
+  const NAME: &'static str = "map generation";
+}
+
+/// Agents being given a movement behaviour when they spawn.
+pub struct BehaviourAssignment;
+impl Subsystem for BehaviourAssignment {
+  const NAME: &'static str = "behaviour assignment";
+}
+
+/// Reseeds all random number generators.
+#[derive(Event)]
+pub struct SetSeed(pub u64);
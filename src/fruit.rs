@@ -1,16 +1,21 @@
-use bevy::color::palettes::tailwind as tw;
 use bevy::prelude::*;
 use bevy_behave::prelude::*;
-use bevy_rand::prelude::{GlobalEntropy, WyRand};
+use rand::Rng;
 
 use crate::{
-  grid::{GridBounds, GridCell},
-  schedule::TickSet,
+  entropy::{FruitSpawning, RngStream},
+  grid::GridCell,
+  schedule::{SimulationTick, TickSet},
+  terrain::{Terrain, TerrainWeights},
+  walls::Walkability,
 };
 
 pub fn fruit_plugin(app: &mut App) {
   app
-    .add_systems(Update, process_spawn_fruit_task.in_set(TickSet))
+    .add_systems(
+      SimulationTick,
+      process_spawn_fruit_task.in_set(TickSet::FruitSpawning),
+    )
     .add_observer(spawn_fruit_spawner);
 }
 
@@ -26,6 +31,10 @@ fn spawn_fruit_spawner(
 
   let mut fruit_spawner = commands.spawn((FruitSpawner {
     target_fruit_number: 20, // maybe do based on grid size?
+    terrain_weights: TerrainWeights::default()
+      .with(Terrain::Mud, 0.3)
+      .with(Terrain::Water, 0.0)
+      .with(Terrain::Road, 0.1),
   },));
 
   let tree = behave!(
@@ -47,11 +56,9 @@ fn process_spawn_fruit_task(
   b_spawn_until_enough: Query<&BehaveCtx, With<SpawnFruitUntilEnough>>,
   q_fruit_spawners: Query<&FruitSpawner>,
   q_fruit: Query<(), With<Fruit>>,
-  r_grid_bounds: Res<GridBounds>,
-  mut r_meshes: ResMut<Assets<Mesh>>,
-  mut r_materials: ResMut<Assets<ColorMaterial>>,
+  walkability: Walkability,
   mut commands: Commands,
-  mut rng: GlobalEntropy<WyRand>,
+  mut rng: ResMut<RngStream<FruitSpawning>>,
 ) {
   let mut n_fruit = None;
   for ctx in b_spawn_until_enough.iter() {
@@ -65,20 +72,35 @@ fn process_spawn_fruit_task(
     }
 
     if n_fruit.unwrap() < spawner.target_fruit_number {
-      let cell = r_grid_bounds.get_random_position(&mut rng);
-      commands.spawn((
-        Fruit::new(2),
-        cell,
-        Mesh2d(r_meshes.add(Rectangle::new(0.3, 0.3))),
-        MeshMaterial2d(r_materials.add(Color::from(tw::RED_600))),
-      ));
+      let mut cell = None;
+      for _ in 0..MAX_SPAWN_ATTEMPTS {
+        let Some(candidate) = walkability.get_random_position(&mut *rng) else {
+          break;
+        };
+        // fruit grows better on some terrains than on others
+        let weight = spawner
+          .terrain_weights
+          .weight(walkability.terrain(&candidate));
+        if rng.gen_bool(weight) {
+          cell = Some(candidate);
+          break;
+        }
+      }
+      let Some(cell) = cell else {
+        warn!("could not find a free cell to spawn fruit in");
+        continue;
+      };
+      commands.spawn((Fruit::new(2), cell));
     }
   }
 }
 
+const MAX_SPAWN_ATTEMPTS: usize = 10;
+
 #[derive(Component)]
 struct FruitSpawner {
   target_fruit_number: usize,
+  terrain_weights: TerrainWeights,
 }
 
 #[derive(Component, Clone)]
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::behaviours::{MovementBehaviourKind, MovementBehaviourWeights, SpawnBehaviours};
+use crate::entropy::SimulationSeed;
+use crate::resizing::GridSizingConfig;
+
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(target_arch = "wasm32")]
+pub use web::web_plugin;
+
+/// Configures the grid sizing, the seed and the behaviours of new agents from a query string (the
+/// page's URL, or the native binary's command line).
+pub fn configure_from_query(app: &mut App, query: &str) {
+  if let Some(config) = GridSizingConfig::from_query(query) {
+    app.insert_resource(config);
+  }
+  if let Some(seed) = SimulationSeed::from_query(query) {
+    app.insert_resource(seed);
+  }
+  if let Some(weights) = MovementBehaviourWeights::from_query(query) {
+    app.insert_resource(SpawnBehaviours(weights));
+  }
+}
+
+/// What the user asked for through the frontend: a button on the page, or a keyboard shortcut
+/// natively.
+#[derive(Debug, Event, Clone, Copy)]
+pub enum FrontendEvent {
+  SpawnAgent,
+  /// For every agent (and the ones that spawn later).
+  SetBehaviour(MovementBehaviourKind),
+  SetSelectedAgentBehaviour(MovementBehaviourKind),
+  /// For every agent in the selected agent's group.
+  SetSelectedGroupBehaviour(MovementBehaviourKind),
+  /// For the agents that spawn from now on.
+  SetSpawnBehaviours(MovementBehaviourWeights),
+  SpawnFruitSpawner,
+  SpawnCoinSpawner,
+  EnableHunger,
+  SpawnWalls,
+  ClearWalls,
+  ScatterTerrain,
+  ClearTerrain,
+  GenerateMaze,
+  GenerateRooms,
+  GenerateCaves,
+  ClearMap,
+  SelectNextAgent,
+  CycleSelectedAgentGroup,
+  ToggleFogOfWar,
+  ToggleTreeVisualiser,
+  ToggleWrap,
+  ToggleDiagonalMovement,
+  CycleDistanceMetric,
+  ToggleHexGrid,
+  CycleResizePolicy,
+  SetWorldSize(usize, usize),
+  FitCameraToWorld,
+  ToggleCameraFollow,
+  // (only sent from JS)
+  SetGridSizingConfig(GridSizingConfig),
+  CycleMovementEasing,
+  TogglePause,
+  StepSimulation,
+  SetSimulationSpeed(f32),
+  SetSeed(u64),
+}
@@ -0,0 +1,255 @@
+use std::sync::OnceLock;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use gloo::events::EventListener;
+use wasm_bindgen::prelude::*;
+
+use super::{FrontendEvent, configure_from_query};
+use crate::behaviours::{MovementBehaviourKind, MovementBehaviourWeights};
+use crate::entropy::SimulationSeed;
+use crate::resizing::GridSizingConfig;
+
+pub fn web_plugin(app: &mut App) {
+  if let Some(query) = startup_query() {
+    configure_from_query(app, &query);
+  }
+
+  // create a channel for communication between web event listeners and Bevy
+  let (sender, receiver) = crossbeam_channel::unbounded::<FrontendEvent>();
+
+  // functions called from JS send to the channel too
+  let _ = WEB_EVENT_SENDER.set(sender.clone());
+
+  // insert channel sender and receiver as separate resources
+  app.insert_resource(GlueSender(sender));
+  app.insert_resource(GlueReceiver(receiver));
+
+  app.add_systems(Update, forward_web_events);
+  app.add_systems(Startup, wire_up_buttons);
+  app.add_systems(Update, show_seed.run_if(resource_changed::<SimulationSeed>));
+}
+
+/// attach click listeners to button elements, and sends them to the channel
+/// (it is not possible to directly send to Bevy from the closure)
+fn wire_up_buttons(sender: Res<GlueSender<FrontendEvent>>) {
+  let mut button_click_mapping = HashMap::new();
+  button_click_mapping.insert("spawn-agent", FrontendEvent::SpawnAgent);
+  button_click_mapping.insert("spawn-agent-toolbar", FrontendEvent::SpawnAgent);
+  button_click_mapping.insert(
+    "walk-lr-naive",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  );
+  button_click_mapping.insert(
+    "walk-lr",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkLeftRight),
+  );
+  button_click_mapping.insert(
+    "walk-lr-toolbar",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkLeftRight),
+  );
+  button_click_mapping.insert(
+    "walk-clockwise",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkClockwise),
+  );
+  button_click_mapping.insert(
+    "walk-clockwise-toolbar",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkClockwise),
+  );
+  button_click_mapping.insert(
+    "move-hunger-based",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::HungerBased),
+  );
+  button_click_mapping.insert(
+    "move-hunger-based-toolbar",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::HungerBased),
+  );
+  button_click_mapping.insert("spawn-fruit-spawner", FrontendEvent::SpawnFruitSpawner);
+  button_click_mapping.insert("spawn-coin-spawner", FrontendEvent::SpawnCoinSpawner);
+  button_click_mapping.insert("enable-hunger", FrontendEvent::EnableHunger);
+  button_click_mapping.insert(
+    "move-to-fruit",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  );
+  button_click_mapping.insert(
+    "move-to-fruit-toolbar",
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  );
+  button_click_mapping.insert(
+    "walk-lr-naive-selected",
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  );
+  button_click_mapping.insert(
+    "walk-lr-selected",
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkLeftRight),
+  );
+  button_click_mapping.insert(
+    "walk-clockwise-selected",
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkClockwise),
+  );
+  button_click_mapping.insert(
+    "move-to-fruit-selected",
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  );
+  button_click_mapping.insert(
+    "move-hunger-based-selected",
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::HungerBased),
+  );
+  button_click_mapping.insert(
+    "walk-lr-naive-group",
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  );
+  button_click_mapping.insert(
+    "walk-lr-group",
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkLeftRight),
+  );
+  button_click_mapping.insert(
+    "walk-clockwise-group",
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkClockwise),
+  );
+  button_click_mapping.insert(
+    "move-to-fruit-group",
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  );
+  button_click_mapping.insert(
+    "move-hunger-based-group",
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::HungerBased),
+  );
+  button_click_mapping.insert(
+    "mixed-population",
+    FrontendEvent::SetSpawnBehaviours(MovementBehaviourWeights::EVEN),
+  );
+  button_click_mapping.insert("spawn-walls", FrontendEvent::SpawnWalls);
+  button_click_mapping.insert("clear-walls", FrontendEvent::ClearWalls);
+  button_click_mapping.insert("scatter-terrain", FrontendEvent::ScatterTerrain);
+  button_click_mapping.insert("clear-terrain", FrontendEvent::ClearTerrain);
+  button_click_mapping.insert("generate-maze", FrontendEvent::GenerateMaze);
+  button_click_mapping.insert("generate-rooms", FrontendEvent::GenerateRooms);
+  button_click_mapping.insert("generate-caves", FrontendEvent::GenerateCaves);
+  button_click_mapping.insert("clear-map", FrontendEvent::ClearMap);
+  button_click_mapping.insert("select-next-agent", FrontendEvent::SelectNextAgent);
+  button_click_mapping.insert("cycle-agent-group", FrontendEvent::CycleSelectedAgentGroup);
+  button_click_mapping.insert("toggle-fog-of-war", FrontendEvent::ToggleFogOfWar);
+  button_click_mapping.insert(
+    "toggle-tree-visualiser",
+    FrontendEvent::ToggleTreeVisualiser,
+  );
+  button_click_mapping.insert("toggle-wrap", FrontendEvent::ToggleWrap);
+  button_click_mapping.insert(
+    "toggle-diagonal-movement",
+    FrontendEvent::ToggleDiagonalMovement,
+  );
+  button_click_mapping.insert("cycle-distance-metric", FrontendEvent::CycleDistanceMetric);
+  button_click_mapping.insert("toggle-hex-grid", FrontendEvent::ToggleHexGrid);
+  button_click_mapping.insert("cycle-resize-policy", FrontendEvent::CycleResizePolicy);
+  button_click_mapping.insert("world-size-100", FrontendEvent::SetWorldSize(100, 100));
+  button_click_mapping.insert("world-size-500", FrontendEvent::SetWorldSize(500, 500));
+  button_click_mapping.insert("fit-camera-to-world", FrontendEvent::FitCameraToWorld);
+  button_click_mapping.insert("toggle-camera-follow", FrontendEvent::ToggleCameraFollow);
+  button_click_mapping.insert("cycle-movement-easing", FrontendEvent::CycleMovementEasing);
+  button_click_mapping.insert("toggle-pause", FrontendEvent::TogglePause);
+  button_click_mapping.insert("step-simulation", FrontendEvent::StepSimulation);
+  button_click_mapping.insert("speed-half", FrontendEvent::SetSimulationSpeed(0.5));
+  button_click_mapping.insert("speed-normal", FrontendEvent::SetSimulationSpeed(1.0));
+  button_click_mapping.insert("speed-double", FrontendEvent::SetSimulationSpeed(2.0));
+  button_click_mapping.insert("speed-quadruple", FrontendEvent::SetSimulationSpeed(4.0));
+
+  let window = web_sys::window().expect("could not get window from web_sys");
+  let document = window.document().expect("could not get document");
+
+  for (id, event) in button_click_mapping.iter() {
+    let Some(dom_button) = document
+      .query_selector(&format!("button#{}", id))
+      .expect("query selector failed")
+    else {
+      // not every page that embeds the demo has every button
+      warn!("button#{} not found, not wiring it up", id);
+      continue;
+    };
+
+    let sender_1 = sender.0.clone();
+    let event_1 = *event;
+    EventListener::new(&dom_button, "click", move |_event| {
+      sender_1.send(event_1).unwrap();
+    })
+    .forget();
+  }
+}
+
+/// the query string of the page's URL
+fn startup_query() -> Option<String> {
+  web_sys::window()?.location().search().ok()
+}
+
+/// shows the active seed on the page, so that a run can be reproduced
+fn show_seed(r_seed: Res<SimulationSeed>) {
+  let Some(element) = web_sys::window()
+    .and_then(|window| window.document())
+    .and_then(|document| document.get_element_by_id("active-seed"))
+  else {
+    return;
+  };
+  element.set_text_content(Some(&r_seed.0.to_string()));
+}
+
+/// configures how the grid is sized (see `GridSizingConfig::from_query` for the format), e.g.
+/// `set_grid_sizing("sizing=fixed-cell-count&columns=30&rows=20")`
+#[wasm_bindgen]
+pub fn set_grid_sizing(query: &str) {
+  let Some(config) = GridSizingConfig::from_query(query) else {
+    warn!("no grid sizing options in {:?}", query);
+    return;
+  };
+  send_web_event(FrontendEvent::SetGridSizingConfig(config));
+}
+
+/// Sets how many times faster than normal the simulation runs (e.g. `0.25` for slow motion).
+#[wasm_bindgen]
+pub fn set_simulation_speed(speed: f32) {
+  send_web_event(FrontendEvent::SetSimulationSpeed(speed));
+}
+
+/// Sets how likely every behaviour is to be given to the agents that spawn from now on (see
+/// `MovementBehaviourWeights::from_query` for the format), e.g.
+/// `set_spawn_behaviours("behaviours=hunger_based:3,walk_clockwise:1")`
+#[wasm_bindgen]
+pub fn set_spawn_behaviours(query: &str) {
+  let Some(weights) = MovementBehaviourWeights::from_query(query) else {
+    warn!("no behaviours in {:?}", query);
+    return;
+  };
+  send_web_event(FrontendEvent::SetSpawnBehaviours(weights));
+}
+
+/// Reseeds all randomness in the simulation. The seed is passed as a string, because JS numbers
+/// can't hold every `u64`.
+#[wasm_bindgen]
+pub fn set_seed(seed: &str) {
+  let Ok(seed) = seed.parse() else {
+    warn!("not a valid seed: {:?}", seed);
+    return;
+  };
+  send_web_event(FrontendEvent::SetSeed(seed));
+}
+
+fn send_web_event(event: FrontendEvent) {
+  let Some(sender) = WEB_EVENT_SENDER.get() else {
+    warn!("the app is not running yet, ignoring {:?}", event);
+    return;
+  };
+  sender.send(event).unwrap();
+}
+
+static WEB_EVENT_SENDER: OnceLock<crossbeam_channel::Sender<FrontendEvent>> = OnceLock::new();
+
+/// consumes WebEvents from the channel and forwards them to the Bevy trigger system
+fn forward_web_events(receiver: ResMut<GlueReceiver<FrontendEvent>>, mut commands: Commands) {
+  while let Ok(event) = receiver.0.try_recv() {
+    commands.trigger(event);
+  }
+}
+
+#[derive(Resource)]
+struct GlueSender<T>(crossbeam_channel::Sender<T>);
+#[derive(Resource)]
+struct GlueReceiver<T>(crossbeam_channel::Receiver<T>);
@@ -1,7 +1,11 @@
-use crate::resizing::{CellSizeChanged, GridSizeChanged};
-use bevy::color::palettes::tailwind as tw;
+use crate::{
+  interpolation::Interpolated,
+  pathfinding::find_nearest,
+  resizing::{CellSizeChanged, DespawnedOnResize, GridSizeChanged, ResizePolicy},
+  walls::{Wall, Walls},
+};
 use bevy::prelude::*;
-use bevy_rand::prelude::{GlobalEntropy, WyRand};
+use bevy::utils::HashSet;
 use rand::Rng;
 
 pub fn grid_plugin(app: &mut App) {
@@ -10,59 +14,120 @@ pub fn grid_plugin(app: &mut App) {
     .init_resource::<CellSize>()
     .add_observer(spawn_grid)
     .add_observer(resize_and_translate_on_cell_size_changed)
+    .add_observer(toggle_wrap)
+    .add_observer(toggle_diagonal_movement)
+    .add_observer(cycle_distance_metric)
     .add_systems(Update, translate_moved_cells);
 }
 
 fn spawn_grid(
   _trigger: Trigger<GridSizeChanged>,
   r_grid_bounds: Res<GridBounds>,
+  r_walls: Res<Walls>,
+  r_resize_policy: Res<ResizePolicy>,
   q_background_cells: Query<Entity, With<Ground>>,
-  mut q_existing_cells: Query<&mut GridCell, Without<Ground>>,
-  mut meshes: ResMut<Assets<Mesh>>,
-  mut materials: ResMut<Assets<ColorMaterial>>,
+  q_walls: Query<(Entity, &GridCell), With<Wall>>,
+  mut q_existing_cells: Query<(Entity, &mut GridCell), (Without<Ground>, Without<Wall>)>,
   mut commands: Commands,
 ) {
-  // clamp existing non-ground cells to new bounds
-  for mut cell in q_existing_cells.iter_mut() {
-    cell.x = cell.x.clamp(
-      r_grid_bounds.left_inclusive(),
-      r_grid_bounds.right_exclusive() - 1,
-    );
+  // despawn walls outside the new bounds (clamping would pile them up on the edges)
+  for (e, cell) in q_walls.iter() {
+    if !r_grid_bounds.contains_unwrapped(cell) {
+      commands.entity(e).despawn_recursive();
+    }
+  }
+
+  // deal with the agents and items that ended up outside the new bounds
+  let mut occupied = q_existing_cells
+    .iter()
+    .map(|(_, cell)| *cell)
+    .filter(|cell| r_grid_bounds.contains_unwrapped(cell))
+    .collect::<HashSet<_>>();
+  for (e, mut cell) in q_existing_cells.iter_mut() {
+    if r_grid_bounds.contains_unwrapped(&cell) {
+      continue;
+    }
 
-    cell.y = cell.y.clamp(
-      r_grid_bounds.top_inclusive(),
-      r_grid_bounds.bottom_exclusive() - 1,
-    );
+    // the closest cell on the grid (wrapping around, if the edges connect)
+    let nearest = if r_grid_bounds.wraps() {
+      r_grid_bounds.wrap(&cell)
+    } else {
+      r_grid_bounds.clamp(&cell)
+    };
+
+    match *r_resize_policy {
+      ResizePolicy::Clamp => *cell = nearest,
+      ResizePolicy::Relocate | ResizePolicy::FixedWorld => {
+        // (a fixed world doesn't change size, but it might have when it was toggled on)
+        let is_free = |c: &GridCell| {
+          r_grid_bounds.contains_unwrapped(c) && !r_walls.contains(c) && !occupied.contains(c)
+        };
+        *cell = find_nearest(&nearest, &r_grid_bounds, is_free).unwrap_or(nearest);
+        occupied.insert(*cell);
+      }
+      ResizePolicy::Despawn => {
+        commands.trigger(DespawnedOnResize {
+          entity: e,
+          cell: *cell,
+        });
+        commands.entity(e).despawn_recursive();
+      }
+    }
   }
 
   // despawn old grid
   for e in q_background_cells.iter() {
-    commands.entity(e).despawn();
+    commands.entity(e).despawn_recursive();
   }
 
   // spawn new grid
   for x in r_grid_bounds.left_inclusive()..r_grid_bounds.right_exclusive() {
     for y in r_grid_bounds.top_inclusive()..r_grid_bounds.bottom_exclusive() {
-      commands.spawn((
-        GridCell::new(x, y),
-        Ground,
-        Mesh2d(meshes.add(Rectangle::new(0.8, 0.8))),
-        MeshMaterial2d(materials.add(Color::from(tw::GRAY_600))),
-      ));
+      let cell = GridCell::new(x, y);
+      commands.spawn((cell, Ground));
     }
   }
   commands.trigger(CellSizeChanged);
 }
 
+fn toggle_wrap(_trigger: Trigger<ToggleWrap>, mut r_grid_bounds: ResMut<GridBounds>) {
+  let wraps = r_grid_bounds.wraps();
+  r_grid_bounds.set_wrap(!wraps);
+}
+
+fn toggle_diagonal_movement(
+  _trigger: Trigger<ToggleDiagonalMovement>,
+  mut r_grid_bounds: ResMut<GridBounds>,
+) {
+  let neighbourhood = match r_grid_bounds.neighbourhood() {
+    Neighbourhood::VonNeumann => Neighbourhood::Moore,
+    Neighbourhood::Moore => Neighbourhood::VonNeumann,
+    // there are no diagonals on a hex grid
+    Neighbourhood::Hexagonal => return,
+  };
+  r_grid_bounds.set_neighbourhood(neighbourhood);
+}
+
+fn cycle_distance_metric(
+  _trigger: Trigger<CycleDistanceMetric>,
+  mut r_grid_bounds: ResMut<GridBounds>,
+) {
+  let distance_metrics = r_grid_bounds.neighbourhood().distance_metrics();
+  let next = distance_metrics
+    .iter()
+    .position(|&m| m == r_grid_bounds.distance_metric())
+    .map_or(0, |i| (i + 1) % distance_metrics.len());
+  r_grid_bounds.set_distance_metric(distance_metrics[next]);
+}
+
 fn translate_moved_cells(
-  mut q_moved: Query<(&GridCell, &mut Transform), Changed<GridCell>>,
+  mut q_moved: Query<(&GridCell, &mut Transform), (Changed<GridCell>, Without<Interpolated>)>,
   r_grid_bounds: Res<GridBounds>,
   r_cell_size: Res<CellSize>,
 ) {
-  let oddness = r_grid_bounds.oddness();
   for (cell, mut transform) in q_moved.iter_mut() {
     transform.translation = Vec3::from((
-      Vec2::from(layout(cell.into(), r_cell_size.0, oddness)),
+      r_grid_bounds.layout(cell, r_cell_size.0),
       transform.translation.z,
     ));
     transform.scale = Vec3::ONE * r_cell_size.0;
@@ -75,10 +140,9 @@ fn resize_and_translate_on_cell_size_changed(
   r_grid_bounds: Res<GridBounds>,
   r_cell_size: Res<CellSize>,
 ) {
-  let oddness = r_grid_bounds.oddness();
   for (cell, mut transform) in q_cells.iter_mut() {
     transform.translation = Vec3::from((
-      Vec2::from(layout(cell.into(), r_cell_size.0, oddness)),
+      r_grid_bounds.layout(cell, r_cell_size.0),
       transform.translation.z,
     ));
     transform.scale = Vec3::ONE * r_cell_size.0;
@@ -87,14 +151,29 @@ fn resize_and_translate_on_cell_size_changed(
 
 fn layout(coordinates: (isize, isize), cell_size: f32, oddness: (bool, bool)) -> (f32, f32) {
   (
-    ((coordinates.0 as f32 + 0.5) * cell_size) as f32
-      - if oddness.0 { 0.5 * cell_size } else { 0.0 },
-    ((coordinates.1 as f32 + 0.5) * cell_size) as f32
-      - if oddness.1 { 0.5 * cell_size } else { 0.0 },
+    (coordinates.0 as f32 + 0.5) * cell_size - if oddness.0 { 0.5 * cell_size } else { 0.0 },
+    (coordinates.1 as f32 + 0.5) * cell_size - if oddness.1 { 0.5 * cell_size } else { 0.0 },
   )
 }
 
-#[derive(Component, Default, PartialEq, Copy, Clone)]
+/// Like [`layout`], but for (pointy-top) hexagons: odd rows are shifted half a cell to the right,
+/// and the rows are packed closer together so they interlock.
+fn hex_layout(coordinates: (isize, isize), cell_size: f32, oddness: (bool, bool)) -> (f32, f32) {
+  let (x, y) = layout(coordinates, cell_size, oddness);
+  // (shifting the even rows a quarter cell to the left keeps the grid centered)
+  let shift = if coordinates.1.rem_euclid(2) == 1 {
+    0.25
+  } else {
+    -0.25
+  };
+  (x + shift * cell_size, y * HEX_ROW_SPACING)
+}
+
+/// The vertical distance between the centers of two rows of hexagons, relative to the horizontal
+/// distance between two neighbouring hexagons (i.e. sqrt(3) / 2).
+pub const HEX_ROW_SPACING: f32 = 0.866_025_4;
+
+#[derive(Component, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
 #[require(Transform)]
 pub struct GridCell {
   pub x: isize,
@@ -110,24 +189,78 @@ impl GridCell {
     Vec2::from(self).distance(Vec2::from(to))
   }
 
-  pub fn neighbours(&self) -> Vec<Self> {
-    vec![
-      GridCell::new(self.x - 1, self.y),
-      GridCell::new(self.x + 1, self.y),
-      GridCell::new(self.x, self.y - 1),
-      GridCell::new(self.x, self.y + 1),
-    ]
+  /// Converts the (offset) coordinates of a hex grid cell to axial coordinates, in which distances
+  /// between hexagons are easy to compute.
+  pub fn to_axial(self) -> (isize, isize) {
+    (self.x - (self.y - self.y.rem_euclid(2)) / 2, self.y)
   }
 
-  pub fn step_to(&mut self, target: &GridCell) {
-    if (self.x - target.x).abs() > (self.y - target.y).abs() {
-      self.x += (target.x - self.x) / (target.x - self.x).abs();
-    } else {
-      self.y += (target.y - self.y) / (target.y - self.y).abs();
+  /// The inverse of [`to_axial`](Self::to_axial).
+  pub fn from_axial((q, r): (isize, isize)) -> Self {
+    Self::new(q + (r - r.rem_euclid(2)) / 2, r)
+  }
+
+  /// Like [`line_to`](Self::line_to), but for hex grid cells: the hexagons that the straight line
+  /// between the centers of the two cells passes through, found by walking the line in cube
+  /// coordinates.
+  pub fn hex_line_to(&self, to: &GridCell) -> Vec<Self> {
+    let (q0, r0) = self.to_axial();
+    let (q1, r1) = to.to_axial();
+    let (dq, dr) = (q1 - q0, r1 - r0);
+    let steps = (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2;
+    if steps == 0 {
+      return vec![*self];
+    }
+
+    // (nudged a little, so that a line along the edge between two hexagons consistently picks one)
+    let (q0, r0) = (q0 as f64 + 1e-6, r0 as f64 + 2e-6);
+    (0..=steps)
+      .map(|i| {
+        let t = i as f64 / steps as f64;
+        let (q, r) = (q0 + dq as f64 * t, r0 + dr as f64 * t);
+        Self::from_axial(round_cube(q, r))
+      })
+      .collect()
+  }
+
+  /// The cells on the straight line from this cell to `to` (both inclusive), using Bresenham's
+  /// line algorithm.
+  pub fn line_to(&self, to: &GridCell) -> Vec<Self> {
+    let (dx, dy) = ((to.x - self.x).abs(), -(to.y - self.y).abs());
+    let (sx, sy) = ((to.x - self.x).signum(), (to.y - self.y).signum());
+    let mut error = dx + dy;
+    let mut current = *self;
+    let mut line = vec![current];
+    while current != *to {
+      let e2 = 2 * error;
+      if e2 >= dy {
+        error += dy;
+        current.x += sx;
+      }
+      if e2 <= dx {
+        error += dx;
+        current.y += sy;
+      }
+      line.push(current);
     }
+    line
   }
 }
 
+/// Rounds fractional axial coordinates to the hexagon they are in (by rounding the cube coordinates
+/// `q`, `r` and `s = -q - r`, and fixing the one that was rounded the most).
+fn round_cube(q: f64, r: f64) -> (isize, isize) {
+  let s = -q - r;
+  let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+  let (diff_q, diff_r, diff_s) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+  if diff_q > diff_r && diff_q > diff_s {
+    rq = -rr - rs;
+  } else if diff_r > diff_s {
+    rr = -rq - rs;
+  }
+  (rq as isize, rr as isize)
+}
+
 impl From<&GridCell> for (isize, isize) {
   fn from(value: &GridCell) -> Self {
     (value.x, value.y)
@@ -146,15 +279,150 @@ pub struct Ground;
 #[derive(Resource, Default, PartialEq, Debug)]
 pub struct CellSize(pub f32);
 
-/// Represents the number of rows / columns of cells should exist.
-#[derive(Resource, Default, PartialEq, Debug)]
+/// Which cells count as adjacent, i.e. can be reached in a single step.
+#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Neighbourhood {
+  /// The 4 orthogonal cells.
+  #[default]
+  VonNeumann,
+  /// The 4 orthogonal and 4 diagonal cells.
+  Moore,
+  /// The 6 surrounding cells of a hex grid.
+  ///
+  /// Hex grid cells are stored in "odd-r" offset coordinates (which keeps the grid rectangular),
+  /// where every odd row is shifted half a cell to the right. Distances, lines of sight and the
+  /// cells around a cell are worked out in axial coordinates (see [`GridCell::to_axial`]).
+  Hexagonal,
+}
+
+impl Neighbourhood {
+  /// The shape of a cell on a grid with this neighbourhood, `size` wide.
+  pub fn cell_mesh(&self, size: f32) -> Mesh {
+    match self {
+      Neighbourhood::Hexagonal => RegularPolygon::new(size / 3f32.sqrt(), 6).into(),
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => Rectangle::new(size, size).into(),
+    }
+  }
+
+  /// The steps to the neighbours of the cell (on a hex grid, these depend on the row it's in).
+  pub fn directions(&self, grid_cell: &GridCell) -> &'static [(isize, isize)] {
+    match self {
+      Neighbourhood::Hexagonal if grid_cell.y.rem_euclid(2) == 1 => {
+        &[(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)]
+      }
+      Neighbourhood::Hexagonal => &[(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)],
+      Neighbourhood::VonNeumann => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+      Neighbourhood::Moore => &[
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+      ],
+    }
+  }
+
+  /// The metric that counts the number of steps between two cells in this neighbourhood.
+  pub fn distance_metric(&self) -> DistanceMetric {
+    match self {
+      Neighbourhood::VonNeumann => DistanceMetric::Manhattan,
+      Neighbourhood::Moore => DistanceMetric::Chebyshev,
+      Neighbourhood::Hexagonal => DistanceMetric::Hex,
+    }
+  }
+
+  /// The distance metrics that make sense for this neighbourhood.
+  pub fn distance_metrics(&self) -> &'static [DistanceMetric] {
+    match self {
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => &[
+        DistanceMetric::Manhattan,
+        DistanceMetric::Chebyshev,
+        DistanceMetric::Euclidean,
+      ],
+      Neighbourhood::Hexagonal => &[DistanceMetric::Hex, DistanceMetric::Euclidean],
+    }
+  }
+}
+
+#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum DistanceMetric {
+  /// The number of orthogonal steps between two cells. The default, as it matches the default von
+  /// Neumann neighbourhood.
+  #[default]
+  Manhattan,
+  Chebyshev,
+  Euclidean,
+  /// The number of steps between two cells on a hex grid.
+  Hex,
+}
+
+/// Represents the number of rows / columns of cells should exist, and the rules for moving between
+/// them.
+///
+/// In wrap mode, the left/right and top/bottom edges are connected (making the world a torus), so
+/// walking off one edge puts you on the opposite one.
+#[derive(Resource, Default, PartialEq, Clone, Debug)]
 pub struct GridBounds {
   width: usize,
   height: usize,
+  wrap: bool,
+  neighbourhood: Neighbourhood,
+  /// How far away cells are for agents looking around (the number of steps to get there is always
+  /// counted using the neighbourhood's own metric).
+  distance_metric: DistanceMetric,
 }
 impl GridBounds {
   pub fn from_size(width: usize, height: usize) -> Self {
-    Self { width, height }
+    Self {
+      width,
+      height,
+      ..default()
+    }
+  }
+
+  /// Takes over the wrap mode, neighbourhood and distance metric of `other`.
+  pub fn with_rules_of(mut self, other: &GridBounds) -> Self {
+    self.wrap = other.wrap;
+    self.neighbourhood = other.neighbourhood;
+    self.distance_metric = other.distance_metric;
+    self
+  }
+
+  pub fn wraps(&self) -> bool {
+    self.wrap
+  }
+
+  pub fn set_wrap(&mut self, wrap: bool) {
+    self.wrap = wrap;
+  }
+
+  pub fn neighbourhood(&self) -> Neighbourhood {
+    self.neighbourhood
+  }
+
+  /// Changes the neighbourhood, along with the distance metric to match it.
+  pub fn set_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+    self.neighbourhood = neighbourhood;
+    self.distance_metric = neighbourhood.distance_metric();
+  }
+
+  pub fn distance_metric(&self) -> DistanceMetric {
+    self.distance_metric
+  }
+
+  pub fn set_distance_metric(&mut self, distance_metric: DistanceMetric) {
+    self.distance_metric = distance_metric;
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
   }
 
   pub fn left_inclusive(&self) -> isize {
@@ -174,20 +442,223 @@ impl GridBounds {
   }
 
   pub fn oddness(&self) -> (bool, bool) {
-    (self.width % 2 != 0, self.height % 2 != 0)
+    (
+      !self.width.is_multiple_of(2),
+      !self.height.is_multiple_of(2),
+    )
   }
 
+  /// Whether the cell is on the grid. In wrap mode every cell is, as it [wraps](Self::wrap) onto
+  /// one.
   pub fn contains(&self, grid_cell: &GridCell) -> bool {
+    self.wrap || self.contains_unwrapped(grid_cell)
+  }
+
+  /// Whether the cell is on the grid, without wrapping it around the edges first.
+  pub fn contains_unwrapped(&self, grid_cell: &GridCell) -> bool {
     grid_cell.x >= self.left_inclusive()
       && grid_cell.x < self.right_exclusive()
       && grid_cell.y >= self.top_inclusive()
       && grid_cell.y < self.bottom_exclusive()
   }
 
-  pub fn get_random_position(&self, rng: &mut GlobalEntropy<WyRand>) -> GridCell {
+  /// The cell on the grid that is closest to the given one.
+  pub fn clamp(&self, grid_cell: &GridCell) -> GridCell {
+    GridCell::new(
+      grid_cell
+        .x
+        .clamp(self.left_inclusive(), self.right_exclusive() - 1),
+      grid_cell
+        .y
+        .clamp(self.top_inclusive(), self.bottom_exclusive() - 1),
+    )
+  }
+
+  /// Maps a cell beyond the edges onto the grid in wrap mode. Without wrapping, this is a no-op.
+  pub fn wrap(&self, grid_cell: &GridCell) -> GridCell {
+    if !self.wrap || self.width == 0 || self.height == 0 {
+      return *grid_cell;
+    }
+    let (left, top) = (self.left_inclusive(), self.top_inclusive());
     GridCell::new(
+      left + (grid_cell.x - left).rem_euclid(self.width as isize),
+      top + (grid_cell.y - top).rem_euclid(self.height as isize),
+    )
+  }
+
+  /// The cell one step from `from` in the given direction.
+  pub fn step(&self, from: &GridCell, (dx, dy): (isize, isize)) -> GridCell {
+    self.wrap(&GridCell::new(from.x + dx, from.y + dy))
+  }
+
+  /// The shortest (x, y) offset from `from` to `to`, which may go across the edges in wrap mode.
+  pub fn offset(&self, from: &GridCell, to: &GridCell) -> (isize, isize) {
+    let (mut dx, mut dy) = (to.x - from.x, to.y - from.y);
+    if self.wrap {
+      let (width, height) = (self.width as isize, self.height as isize);
+      dx -= width * (dx as f32 / width as f32).round() as isize;
+      dy -= height * (dy as f32 / height as f32).round() as isize;
+    }
+    (dx, dy)
+  }
+
+  /// The shortest vector from the center of `from` to the center of `to`, in units of cells.
+  pub fn displacement(&self, from: &GridCell, to: &GridCell) -> Vec2 {
+    let (dx, dy) = self.offset(from, to);
+    if self.neighbourhood != Neighbourhood::Hexagonal {
+      return Vec2::new(dx as f32, dy as f32);
+    }
+    let row_shift = |y: isize| if y.rem_euclid(2) == 1 { 0.5 } else { 0.0 };
+    Vec2::new(
+      dx as f32 + row_shift(from.y + dy) - row_shift(from.y),
+      dy as f32 * HEX_ROW_SPACING,
+    )
+  }
+
+  /// The distance between two cells, according to the distance metric.
+  pub fn distance(&self, from: &GridCell, to: &GridCell) -> f32 {
+    self.measure(self.distance_metric, from, to)
+  }
+
+  /// The least number of steps it takes to get from one cell to the other (ignoring walls).
+  pub fn steps(&self, from: &GridCell, to: &GridCell) -> usize {
+    self.measure(self.neighbourhood.distance_metric(), from, to) as usize
+  }
+
+  fn measure(&self, distance_metric: DistanceMetric, from: &GridCell, to: &GridCell) -> f32 {
+    let (dx, dy) = self.offset(from, to);
+    match distance_metric {
+      DistanceMetric::Manhattan => (dx.unsigned_abs() + dy.unsigned_abs()) as f32,
+      DistanceMetric::Chebyshev => dx.unsigned_abs().max(dy.unsigned_abs()) as f32,
+      DistanceMetric::Euclidean => self.displacement(from, to).length(),
+      DistanceMetric::Hex => {
+        // (going the short way around, in wrap mode)
+        let (q0, r0) = from.to_axial();
+        let (q1, r1) = GridCell::new(from.x + dx, from.y + dy).to_axial();
+        let (dq, dr) = (q1 - q0, r1 - r0);
+        (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) as f32 / 2.0
+      }
+    }
+  }
+
+  /// The cells on the straight line between two cells (both inclusive), as the grid is laid out.
+  pub fn line(&self, from: &GridCell, to: &GridCell) -> Vec<GridCell> {
+    match self.neighbourhood {
+      Neighbourhood::Hexagonal => from.hex_line_to(to),
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => from.line_to(to),
+    }
+  }
+
+  /// The cells that can be within `radius` of `center` (by the distance metric): the square of
+  /// cells that spans `radius` cells in each direction, or on a hex grid, the hexagon of cells that
+  /// are at most as many steps away as can fit in `radius`. In wrap mode, the cells may reach
+  /// across the edges, in which case they are given as seen from `center` (i.e. not wrapped onto
+  /// the grid).
+  pub fn cells_around(&self, center: &GridCell, radius: usize) -> Vec<GridCell> {
+    let radius = radius as isize;
+    if self.neighbourhood != Neighbourhood::Hexagonal {
+      return ((center.x - radius)..=(center.x + radius))
+        .flat_map(|x| ((center.y - radius)..=(center.y + radius)).map(move |y| GridCell::new(x, y)))
+        .collect();
+    }
+
+    let reach = match self.distance_metric {
+      // (the rows are closer together than the cells in a row, so more steps fit in the radius)
+      DistanceMetric::Euclidean => (radius as f32 / HEX_ROW_SPACING) as isize,
+      DistanceMetric::Hex | DistanceMetric::Manhattan | DistanceMetric::Chebyshev => radius,
+    };
+    let (q, r) = center.to_axial();
+    (-reach..=reach)
+      .flat_map(|dq| {
+        ((-reach).max(-dq - reach)..=reach.min(-dq + reach))
+          .map(move |dr| GridCell::from_axial((q + dq, r + dr)))
+      })
+      .collect()
+  }
+
+  /// The cells in the neighbourhood of the cell (which may be beyond the edges, unless in wrap
+  /// mode).
+  pub fn neighbours(&self, grid_cell: &GridCell) -> Vec<GridCell> {
+    self
+      .neighbourhood
+      .directions(grid_cell)
+      .iter()
+      .map(|&direction| self.step(grid_cell, direction))
+      .collect()
+  }
+
+  /// Where the center of the cell ends up on screen.
+  pub fn layout(&self, grid_cell: &GridCell, cell_size: f32) -> Vec2 {
+    let layout = match self.neighbourhood {
+      Neighbourhood::Hexagonal => hex_layout,
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => layout,
+    };
+    Vec2::from(layout(grid_cell.into(), cell_size, self.oddness()))
+  }
+
+  /// The cell that a position on screen is in, i.e. the inverse of [`layout`](Self::layout). The
+  /// cell is not necessarily on the grid.
+  pub fn cell_at(&self, position: Vec2, cell_size: f32) -> GridCell {
+    let (odd_x, odd_y) = self.oddness();
+    let offset = |odd: bool| if odd { 0.5 } else { 0.0 };
+    let column = |x: f32| (x / cell_size + offset(odd_x)).floor() as isize;
+    match self.neighbourhood {
+      Neighbourhood::Hexagonal => {
+        // the rows interlock, so the position is in one of the two rows it lies between: the one
+        // with the closest cell center
+        let row = position.y / HEX_ROW_SPACING / cell_size + offset(odd_y) - 0.5;
+        [row.floor(), row.ceil()]
+          .into_iter()
+          .map(|row| {
+            let row = row as isize;
+            let shift = if row.rem_euclid(2) == 1 { 0.25 } else { -0.25 };
+            GridCell::new(column(position.x - shift * cell_size), row)
+          })
+          .min_by(|a, b| {
+            let distance = |cell| self.layout(cell, cell_size).distance_squared(position);
+            distance(a).total_cmp(&distance(b))
+          })
+          .unwrap()
+      }
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => GridCell::new(
+        column(position.x),
+        (position.y / cell_size + offset(odd_y)).floor() as isize,
+      ),
+    }
+  }
+
+  /// The size of the whole grid on screen.
+  pub fn world_size(&self, cell_size: f32) -> Vec2 {
+    let n_cells = match self.neighbourhood {
+      // (every other row sticks out half a cell, and the rows interlock)
+      Neighbourhood::Hexagonal => Vec2::new(
+        self.width as f32 + 0.5,
+        self.height as f32 * HEX_ROW_SPACING,
+      ),
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => {
+        Vec2::new(self.width as f32, self.height as f32)
+      }
+    };
+    n_cells * cell_size
+  }
+
+  /// A random cell on the grid, or `None` if the grid has no cells.
+  pub fn get_random_position(&self, rng: &mut impl Rng) -> Option<GridCell> {
+    if self.width == 0 || self.height == 0 {
+      return None;
+    }
+    Some(GridCell::new(
       rng.gen_range(self.left_inclusive()..self.right_exclusive()),
       rng.gen_range(self.top_inclusive()..self.bottom_exclusive()),
-    )
+    ))
   }
 }
+
+#[derive(Event)]
+pub struct ToggleWrap;
+
+#[derive(Event)]
+pub struct ToggleDiagonalMovement;
+
+#[derive(Event)]
+pub struct CycleDistanceMetric;
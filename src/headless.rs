@@ -0,0 +1,358 @@
+//! Runs the simulation without a window, for a fixed number of ticks, and records what happened
+//! every tick. This is meant for comparing behaviours over many seeded runs, e.g.
+//! `cargo run --release --bin headless -- experiment.json`.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::window::ExitCondition;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::Agent;
+use crate::behaviours::{
+  AssignedMovementBehaviour, MovementBehaviourKind, MovementBehaviourWeights, PickedUp, TargetKind,
+  behaviour_trees_loaded,
+};
+use crate::entropy::SimulationSeed;
+use crate::glue::FrontendEvent;
+use crate::points::Points;
+use crate::schedule::SimTick;
+
+/// What to simulate, read from a JSON file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HeadlessConfig {
+  /// How many ticks every run lasts.
+  pub ticks: u64,
+  /// How many runs to do. Every run uses the next seed, starting at `seed`.
+  #[serde(default = "one")]
+  pub runs: u64,
+  #[serde(default)]
+  pub seed: u64,
+  pub width: usize,
+  pub height: usize,
+  pub agents: usize,
+  pub behaviour: Behaviours,
+  #[serde(default)]
+  pub fruit_spawners: usize,
+  #[serde(default)]
+  pub coin_spawners: usize,
+  #[serde(default)]
+  pub hunger: bool,
+  #[serde(default)]
+  pub walls: bool,
+  #[serde(default)]
+  pub terrain: bool,
+  #[serde(default)]
+  pub map: Option<Map>,
+  /// Where the statistics go: CSV if the file name ends in `.csv`, JSON otherwise.
+  pub output: PathBuf,
+  /// The directory that assets (like behaviour trees) are loaded from. By default, the `assets`
+  /// directory of this crate, so that the runner can be started from anywhere (and not only with
+  /// `cargo run`, which tells bevy where the crate is).
+  #[serde(default = "crate_assets")]
+  pub assets: PathBuf,
+}
+
+fn one() -> u64 {
+  1
+}
+
+fn crate_assets() -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+impl HeadlessConfig {
+  /// Checks for settings that can't be simulated.
+  pub fn validate(&self) -> Result<(), String> {
+    if self.width == 0 || self.height == 0 {
+      return Err(format!(
+        "the world can't be {}x{} cells",
+        self.width, self.height
+      ));
+    }
+    if let Behaviours::Mixed(weights) = &self.behaviour {
+      Behaviours::weights(weights).map_err(|error| format!("invalid behaviour mix: {error}"))?;
+    }
+    Ok(())
+  }
+}
+
+/// The behaviour of every agent (e.g. `"hunger_based"`), or how likely every agent is to get each
+/// of a mix of them (e.g. `{"hunger_based": 1, "walk_clockwise": 1}`).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Behaviours {
+  All(MovementBehaviourKind),
+  Mixed(HashMap<MovementBehaviourKind, f32>),
+}
+
+impl Behaviours {
+  /// The behaviours that agents can get, in a fixed order.
+  fn kinds(&self) -> Vec<MovementBehaviourKind> {
+    MovementBehaviourKind::ALL
+      .into_iter()
+      .filter(|kind| match self {
+        Self::All(behaviour) => behaviour == kind,
+        Self::Mixed(weights) => weights.get(kind).is_some_and(|weight| *weight > 0.0),
+      })
+      .collect()
+  }
+
+  fn weights(
+    weights: &HashMap<MovementBehaviourKind, f32>,
+  ) -> Result<MovementBehaviourWeights, String> {
+    weights
+      .iter()
+      .fold(
+        MovementBehaviourWeights::NONE,
+        |weights, (behaviour, weight)| weights.with(*behaviour, *weight),
+      )
+      .validate()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Map {
+  Maze,
+  Rooms,
+  Caves,
+}
+
+/// The state of the agents with one of the behaviours, after a tick of a run. Every tick has one
+/// of these for every behaviour in the config, so that a mix of behaviours can be compared.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TickStatistics {
+  pub run: u64,
+  pub seed: u64,
+  pub tick: u64,
+  pub behaviour: MovementBehaviourKind,
+  pub alive_agents: usize,
+  /// The points of the agents that are still alive.
+  pub points: usize,
+  /// How much fruit has been eaten so far.
+  pub fruit_eaten: usize,
+  /// How many coins have been collected so far.
+  pub coins_collected: usize,
+}
+
+/// Does all the runs in the config, and writes their statistics to its output. Fails if the config
+/// is invalid, if a behaviour tree can't be loaded, or if the output can't be written.
+pub fn run(config: &HeadlessConfig) -> Result<(), String> {
+  let mut statistics = Vec::new();
+  for run in 0..config.runs {
+    statistics.extend(simulate(config, run)?);
+  }
+  write_statistics(&config.output, &statistics)
+    .map_err(|error| format!("could not write {}: {error}", config.output.display()))
+}
+
+/// Simulates a single run, and returns its statistics for every tick (or why it couldn't).
+pub fn simulate(config: &HeadlessConfig, run: u64) -> Result<Vec<TickStatistics>, String> {
+  config.validate()?;
+  let seed = config.seed.wrapping_add(run);
+  // (bevy resolves relative paths from the executable, rather than from where it was started)
+  let assets = std::path::absolute(&config.assets)
+    .map_err(|error| format!("invalid assets path {}: {error}", config.assets.display()))?;
+  let mut app = build_headless_app(seed, &assets);
+
+  // the clock only advances when it is stepped
+  send(app.world_mut(), FrontendEvent::TogglePause);
+  // (the random number generators are seeded in the first update)
+  app.update();
+  set_up(app.world_mut(), config);
+  // (otherwise the first ticks would depend on how fast the trees load)
+  while !behaviour_trees_loaded(app.world())? {
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    app.update();
+  }
+
+  let behaviours = config.behaviour.kinds();
+  Ok(
+    (0..config.ticks)
+      .flat_map(|_| {
+        send(app.world_mut(), FrontendEvent::StepSimulation);
+        app.update();
+        collect_statistics(app.world_mut(), run, seed, &behaviours)
+      })
+      .collect(),
+  )
+}
+
+fn build_headless_app(seed: u64, assets: &Path) -> App {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    // (the behaviour trees are assets, which shouldn't change halfway through a run)
+    .add_plugins(AssetPlugin {
+      file_path: assets.to_string_lossy().into_owned(),
+      watch_for_changes_override: Some(false),
+      ..default()
+    })
+    // no window, but the grid still listens for window events
+    .add_plugins(WindowPlugin {
+      primary_window: None,
+      exit_condition: ExitCondition::DontExit,
+      close_when_requested: false,
+    })
+    .add_plugins(crate::simulation_plugin)
+    .init_resource::<PickUpCounts>()
+    .add_observer(count_pick_ups)
+    .insert_resource(SimulationSeed(seed));
+  app.finish();
+  app.cleanup();
+  app
+}
+
+/// Does what a user would do on the page, in the order they would do it.
+fn set_up(world: &mut World, config: &HeadlessConfig) {
+  send(
+    world,
+    FrontendEvent::SetWorldSize(config.width, config.height),
+  );
+  if config.walls {
+    send(world, FrontendEvent::SpawnWalls);
+  }
+  if let Some(map) = config.map {
+    send(
+      world,
+      match map {
+        Map::Maze => FrontendEvent::GenerateMaze,
+        Map::Rooms => FrontendEvent::GenerateRooms,
+        Map::Caves => FrontendEvent::GenerateCaves,
+      },
+    );
+  }
+  if config.terrain {
+    send(world, FrontendEvent::ScatterTerrain);
+  }
+  // (a mix has to be set before the agents spawn, they draw their behaviour when they do)
+  if let Behaviours::Mixed(weights) = &config.behaviour {
+    // (checked before the run started)
+    if let Ok(weights) = Behaviours::weights(weights) {
+      send(world, FrontendEvent::SetSpawnBehaviours(weights));
+    }
+  }
+  for _ in 0..config.agents {
+    send(world, FrontendEvent::SpawnAgent);
+  }
+  if let Behaviours::All(behaviour) = config.behaviour {
+    send(world, FrontendEvent::SetBehaviour(behaviour));
+  }
+  if config.hunger {
+    send(world, FrontendEvent::EnableHunger);
+  }
+  for _ in 0..config.fruit_spawners {
+    send(world, FrontendEvent::SpawnFruitSpawner);
+  }
+  for _ in 0..config.coin_spawners {
+    send(world, FrontendEvent::SpawnCoinSpawner);
+  }
+}
+
+/// Sends an event like the frontend would, and applies what it causes right away (rather than
+/// during the next update, which might already be too late to take effect in that update).
+fn send(world: &mut World, event: FrontendEvent) {
+  world.trigger(event);
+  world.flush();
+}
+
+fn collect_statistics(
+  world: &mut World,
+  run: u64,
+  seed: u64,
+  behaviours: &[MovementBehaviourKind],
+) -> Vec<TickStatistics> {
+  let tick = world.resource::<SimTick>().0;
+  let mut statistics = behaviours
+    .iter()
+    .map(|behaviour| {
+      let pick_ups = world
+        .resource::<PickUpCounts>()
+        .0
+        .get(behaviour)
+        .copied()
+        .unwrap_or_default();
+      TickStatistics {
+        run,
+        seed,
+        tick,
+        behaviour: *behaviour,
+        alive_agents: 0,
+        points: 0,
+        fruit_eaten: pick_ups.fruit,
+        coins_collected: pick_ups.coins,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  for (assigned, agent_points) in world
+    .query_filtered::<(&AssignedMovementBehaviour, Option<&Points>), With<Agent>>()
+    .iter(world)
+  {
+    let Some(row) = statistics
+      .iter_mut()
+      .find(|row| row.behaviour == assigned.0)
+    else {
+      continue;
+    };
+    row.alive_agents += 1;
+    row.points += agent_points.map_or(0, Points::current);
+  }
+  statistics
+}
+
+/// Counts what the agents with each behaviour picked up.
+fn count_pick_ups(
+  trigger: Trigger<PickedUp>,
+  q_agents: Query<&AssignedMovementBehaviour>,
+  mut r_pick_up_counts: ResMut<PickUpCounts>,
+) {
+  let Ok(assigned) = q_agents.get(trigger.entity()) else {
+    return;
+  };
+  let counts = r_pick_up_counts.0.entry(assigned.0).or_default();
+  match trigger.event().0 {
+    TargetKind::Fruit => counts.fruit += 1,
+    TargetKind::Coins => counts.coins += 1,
+  }
+}
+
+#[derive(Resource, Default)]
+struct PickUpCounts(HashMap<MovementBehaviourKind, PickUps>);
+
+#[derive(Default, Clone, Copy)]
+struct PickUps {
+  fruit: usize,
+  coins: usize,
+}
+
+fn write_statistics(path: &Path, statistics: &[TickStatistics]) -> std::io::Result<()> {
+  let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+  if path.extension().is_some_and(|extension| extension == "csv") {
+    writeln!(
+      file,
+      "run,seed,tick,behaviour,alive_agents,points,fruit_eaten,coins_collected"
+    )?;
+    for row in statistics {
+      writeln!(
+        file,
+        "{},{},{},{},{},{},{},{}",
+        row.run,
+        row.seed,
+        row.tick,
+        row.behaviour.name(),
+        row.alive_agents,
+        row.points,
+        row.fruit_eaten,
+        row.coins_collected
+      )?;
+    }
+  } else {
+    serde_json::to_writer_pretty(&mut file, statistics)?;
+  }
+  file.flush()
+}
@@ -1,23 +1,28 @@
-use bevy::color::palettes::tailwind as tw;
 use bevy::prelude::*;
 
 use crate::agent::Agent;
-use crate::schedule::HungerTickSet;
+use crate::schedule::{HungerTickSet, PrepareTickSet, SimulationTick};
+use crate::visuals::Visuals;
 
 pub fn hunger_plugin(app: &mut App) {
   app
     .init_resource::<HungerEnabled>()
+    .add_systems(SimulationTick, process_hunger.in_set(HungerTickSet))
     .add_systems(
-      Update,
-      (process_hunger, update_hunger_indicators)
-        .chain()
-        .in_set(HungerTickSet),
+      SimulationTick,
+      insert_hunger_on_agent_spawn.in_set(PrepareTickSet::Hunger),
     )
-    .add_systems(Update, insert_hunger_on_agent_spawn)
-    .add_systems(Update, insert_indicator_on_hunger_spawn)
     .add_observer(on_enable_hunger);
 }
 
+/// Shows how much hunger every agent has left, as a bar next to it.
+pub fn hunger_indicator_plugin(app: &mut App) {
+  app.add_systems(
+    Update,
+    (insert_indicator_on_hunger_spawn, update_hunger_indicators).chain(),
+  );
+}
+
 fn on_enable_hunger(
   _trigger: Trigger<EnableHunger>,
   q_agents: Query<Entity, With<Agent>>,
@@ -62,16 +67,14 @@ fn process_hunger(mut q_agents: Query<(Entity, &mut Hunger), With<Agent>>, mut c
 
 fn insert_indicator_on_hunger_spawn(
   q_agents_with_hunger: Query<Entity, Added<Hunger>>,
-  mut r_meshes: ResMut<Assets<Mesh>>,
-  mut r_materials: ResMut<Assets<ColorMaterial>>,
+  r_visuals: Res<Visuals>,
   mut commands: Commands,
 ) {
   for agent in q_agents_with_hunger.iter() {
     commands.entity(agent).with_child((
       HungerIndicator,
-      MeshMaterial2d(r_materials.add(Color::from(tw::RED_500))),
-      // mesh is translated so that it scales from the side rather than from the center
-      Mesh2d(r_meshes.add(Mesh::from(Rectangle::new(0.15, 0.8)).translated_by(Vec3::Y * 0.4))),
+      MeshMaterial2d(r_visuals.hunger_material.clone()),
+      Mesh2d(r_visuals.indicator_mesh.clone()),
       Transform::from_xyz(-0.3, -0.4, 0.1),
     ));
   }
@@ -111,6 +114,14 @@ impl Hunger {
     self.remaining = (self.remaining + nutritional_value).clamp(0, self.capacity);
   }
 
+  pub fn remaining(&self) -> usize {
+    self.remaining
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
   pub fn fraction_left(&self) -> f32 {
     (self.remaining as f32) / (self.capacity as f32)
   }
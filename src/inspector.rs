@@ -0,0 +1,166 @@
+use bevy::input::touch::Touches;
+use bevy::prelude::*;
+
+use crate::{
+  agent::{Agent, AgentGroup, SelectedAgent},
+  behaviours::{MovementBehaviour, Target, WalkInDirectionUntilOutOfBounds},
+  coins::Coin,
+  fruit::Fruit,
+  grid::{CellSize, GridBounds, GridCell},
+  hunger::Hunger,
+  points::Points,
+};
+
+/// Selects agents by clicking (or tapping) them, and shows what's going on with the selected
+/// agent in a panel.
+pub fn inspector_plugin(app: &mut App) {
+  app
+    .add_systems(Startup, spawn_inspector)
+    .add_systems(Update, (select_clicked_agent, update_inspector).chain());
+}
+
+fn spawn_inspector(mut commands: Commands) {
+  commands.spawn((
+    Inspector,
+    Node {
+      position_type: PositionType::Absolute,
+      top: Val::Px(8.0),
+      right: Val::Px(8.0),
+      padding: UiRect::all(Val::Px(8.0)),
+      ..default()
+    },
+    BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+    Text::default(),
+    TextFont::from_font_size(14.0),
+    Visibility::Hidden,
+  ));
+}
+
+/// Selects the agent in the cell that was clicked. Clicking a cell with several agents in it
+/// again selects the next one, and clicking a cell without agents clears the selection. (Dragging
+/// moves the camera, so a press only counts as a click if the pointer barely moved.)
+fn select_clicked_agent(
+  r_mouse_buttons: Res<ButtonInput<MouseButton>>,
+  r_touches: Res<Touches>,
+  q_window: Single<&Window>,
+  q_camera: Single<(&Camera, &GlobalTransform), With<Camera2d>>,
+  q_agents: Query<(Entity, &GridCell), With<Agent>>,
+  r_grid_bounds: Res<GridBounds>,
+  r_cell_size: Res<CellSize>,
+  mut r_selected_agent: ResMut<SelectedAgent>,
+  mut press_position: Local<Option<Vec2>>,
+) {
+  let mut click = None;
+  if r_mouse_buttons.just_pressed(MouseButton::Left) {
+    *press_position = q_window.cursor_position();
+  }
+  if r_mouse_buttons.just_released(MouseButton::Left) {
+    let release_position = q_window.cursor_position();
+    if let (Some(pressed), Some(released)) = (press_position.take(), release_position)
+      && pressed.distance(released) < MAX_CLICK_DISTANCE
+    {
+      click = Some(released);
+    }
+  }
+  for touch in r_touches.iter_just_released() {
+    if touch.distance().length() < MAX_CLICK_DISTANCE {
+      click = Some(touch.position());
+    }
+  }
+
+  let Some(click) = click else {
+    return;
+  };
+  let (camera, camera_transform) = *q_camera;
+  let Ok(position) = camera.viewport_to_world_2d(camera_transform, click) else {
+    return;
+  };
+  let cell = r_grid_bounds.cell_at(position, r_cell_size.0);
+
+  let mut agents = q_agents
+    .iter()
+    .filter(|(_, agent_cell)| **agent_cell == cell)
+    .map(|(agent, _)| agent)
+    .collect::<Vec<_>>();
+  agents.sort();
+  let next = match r_selected_agent.0 {
+    Some(selected) if agents.contains(&selected) => agents
+      .iter()
+      .find(|&&agent| agent > selected)
+      .or(agents.first()),
+    _ => agents.first(),
+  };
+  r_selected_agent.set_if_neq(SelectedAgent(next.copied()));
+}
+
+fn update_inspector(
+  q_inspector: Single<(&mut Text, &mut Visibility), With<Inspector>>,
+  q_agents: Query<
+    (
+      &GridCell,
+      Option<&AgentGroup>,
+      Option<&Hunger>,
+      Option<&Points>,
+      Option<&Target>,
+      Option<&Children>,
+      Has<WalkInDirectionUntilOutOfBounds>,
+    ),
+    With<Agent>,
+  >,
+  q_items: Query<(&GridCell, Has<Fruit>, Has<Coin>)>,
+  q_movement_behaviours: Query<&Name, With<MovementBehaviour>>,
+  r_selected_agent: Res<SelectedAgent>,
+) {
+  let (mut text, mut visibility) = q_inspector.into_inner();
+  let Some((agent, (cell, group, hunger, points, target, children, walks_naively))) =
+    r_selected_agent
+      .0
+      .and_then(|agent| Some((agent, q_agents.get(agent).ok()?)))
+  else {
+    visibility.set_if_neq(Visibility::Hidden);
+    return;
+  };
+  visibility.set_if_neq(Visibility::Inherited);
+
+  let group = group.map_or("-".to_string(), |group| group.0.to_string());
+  let hunger = hunger.map_or("-".to_string(), |hunger| {
+    format!("{}/{}", hunger.remaining(), hunger.capacity())
+  });
+  let points = points.map_or("-".to_string(), |points| {
+    format!("{}/{}", points.current(), points.goal())
+  });
+  let target = match target.and_then(|target| q_items.get(target.0).ok()) {
+    Some((item_cell, true, _)) => format!("fruit at ({}, {})", item_cell.x, item_cell.y),
+    Some((item_cell, _, true)) => format!("coins at ({}, {})", item_cell.x, item_cell.y),
+    _ => "-".to_string(),
+  };
+  let movement = children
+    .into_iter()
+    .flatten()
+    .find_map(|child| q_movement_behaviours.get(*child).ok())
+    .map(Name::to_string)
+    .or(walks_naively.then(|| "Walk left right (naive)".to_string()))
+    .unwrap_or("-".to_string());
+
+  let inspected = format!(
+    "Agent {agent}\n\
+     cell: ({}, {})\n\
+     group: {group}\n\
+     hunger: {hunger}\n\
+     points: {points}\n\
+     target: {target}\n\
+     movement: {movement}",
+    cell.x, cell.y
+  );
+  // (only touched when it changes, so that the text isn't laid out again every frame)
+  if text.0 != inspected {
+    text.0 = inspected;
+  }
+}
+
+/// How far (in logical pixels) the pointer can move between pressing and releasing, for it to
+/// still be a click rather than a drag.
+const MAX_CLICK_DISTANCE: f32 = 5.0;
+
+#[derive(Component)]
+struct Inspector;
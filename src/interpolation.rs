@@ -0,0 +1,106 @@
+use core::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+  grid::{CellSize, GridBounds, GridCell},
+  schedule::SimulationClock,
+};
+
+pub fn interpolation_plugin(app: &mut App) {
+  app
+    .init_resource::<MovementEasing>()
+    .add_systems(Update, (start_interpolation, interpolate_moves).chain())
+    .add_observer(cycle_movement_easing);
+}
+
+fn start_interpolation(mut q_moved: Query<(&GridCell, &mut Interpolated), Changed<GridCell>>) {
+  for (cell, mut interpolated) in q_moved.iter_mut() {
+    // only steps to a neighbouring cell are animated: anything further is a wrap-around (which
+    // would slide across the whole grid) or a relocation
+    let is_step = interpolated
+      .to
+      .is_some_and(|to| (to.x - cell.x).abs() <= 1 && (to.y - cell.y).abs() <= 1);
+    interpolated.from = if is_step { interpolated.to } else { None };
+    interpolated.to = Some(*cell);
+    interpolated.elapsed = Duration::ZERO;
+  }
+}
+
+/// Moves entities from their previous cell to their current cell over the course of a tick.
+fn interpolate_moves(
+  mut q_interpolated: Query<(&GridCell, &mut Interpolated, &mut Transform)>,
+  r_grid_bounds: Res<GridBounds>,
+  r_cell_size: Res<CellSize>,
+  r_clock: Res<SimulationClock>,
+  r_movement_easing: Res<MovementEasing>,
+  r_time: Res<Time>,
+) {
+  let tick_duration = r_clock.tick_duration();
+  let resized = r_grid_bounds.is_changed() || r_cell_size.is_changed();
+  for (cell, mut interpolated, mut transform) in q_interpolated.iter_mut() {
+    let finished = interpolated.elapsed > tick_duration;
+    if finished && !resized {
+      continue;
+    }
+    interpolated.elapsed += r_time.delta();
+
+    let to = r_grid_bounds.layout(cell, r_cell_size.0);
+    let position = match interpolated.from {
+      Some(from) => {
+        // (the layout is recomputed every frame, so this follows along with resizes)
+        let from = r_grid_bounds.layout(&from, r_cell_size.0);
+        let t = interpolated.elapsed.as_secs_f32() / tick_duration.as_secs_f32();
+        from.lerp(to, r_movement_easing.ease(t.min(1.0)))
+      }
+      None => to,
+    };
+    transform.translation = Vec3::from((position, transform.translation.z));
+    transform.scale = Vec3::ONE * r_cell_size.0;
+  }
+}
+
+fn cycle_movement_easing(
+  _trigger: Trigger<CycleMovementEasing>,
+  mut r_movement_easing: ResMut<MovementEasing>,
+) {
+  *r_movement_easing = match *r_movement_easing {
+    MovementEasing::Off => MovementEasing::Linear,
+    MovementEasing::Linear => MovementEasing::Smooth,
+    MovementEasing::Smooth => MovementEasing::Off,
+  };
+  info!("movement easing: {:?}", *r_movement_easing);
+}
+
+/// Slides an entity from cell to cell, rather than jumping to its new cell as soon as it moves.
+#[derive(Component, Default)]
+pub struct Interpolated {
+  from: Option<GridCell>,
+  to: Option<GridCell>,
+  elapsed: Duration,
+}
+
+/// How entities speed up and slow down when moving between cells.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub enum MovementEasing {
+  /// Jump to the next cell right away.
+  Off,
+  Linear,
+  /// Speed up at the start of the step and slow down at the end.
+  #[default]
+  Smooth,
+}
+
+impl MovementEasing {
+  /// Maps the progress of a step (from 0 to 1) to how far along the entity is drawn.
+  pub fn ease(&self, t: f32) -> f32 {
+    match self {
+      MovementEasing::Off => 1.0,
+      MovementEasing::Linear => t,
+      MovementEasing::Smooth => t * t * (3.0 - 2.0 * t),
+    }
+  }
+}
+
+#[derive(Event)]
+pub struct CycleMovementEasing;
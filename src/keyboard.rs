@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use behave_blog_demo::{
+  FrontendEvent, MovementBehaviourKind, MovementBehaviourWeights, SimulationSeed,
+};
+
+pub fn keyboard_plugin(app: &mut App) {
+  app.add_systems(Startup, log_shortcuts);
+  app.add_systems(Update, trigger_shortcuts);
+  app.add_systems(Update, show_seed.run_if(resource_changed::<SimulationSeed>));
+}
+
+/// the first command line argument, in the same format as the web page's query string, e.g.
+/// `cargo run -- "seed=42&sizing=fixed-cell-count&columns=30&rows=20"`
+pub fn startup_query() -> Option<String> {
+  std::env::args().nth(1)
+}
+
+fn log_shortcuts() {
+  info!("keyboard shortcuts:");
+  for (key, event) in SHORTCUTS {
+    info!("  {:?}: {:?}", key, event);
+  }
+  for (key, event) in SHIFT_SHORTCUTS {
+    info!("  Shift+{:?}: {:?}", key, event);
+  }
+  for (key, event) in CONTROL_SHORTCUTS {
+    info!("  Ctrl+{:?}: {:?}", key, event);
+  }
+}
+
+/// the keyboard shortcuts do what the buttons on the web page do
+fn trigger_shortcuts(r_keys: Res<ButtonInput<KeyCode>>, mut commands: Commands) {
+  let shortcuts = if r_keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+    CONTROL_SHORTCUTS
+  } else if r_keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+    SHIFT_SHORTCUTS
+  } else {
+    SHORTCUTS
+  };
+  for (key, event) in shortcuts {
+    if r_keys.just_pressed(*key) {
+      commands.trigger(*event);
+    }
+  }
+}
+
+/// shows the active seed in the window title, so that a run can be reproduced
+fn show_seed(r_seed: Res<SimulationSeed>, mut q_window: Single<&mut Window, With<PrimaryWindow>>) {
+  q_window.title = format!("Behave demo (seed {})", r_seed.0);
+}
+
+const SHORTCUTS: &[(KeyCode, FrontendEvent)] = &[
+  (KeyCode::KeyA, FrontendEvent::SpawnAgent),
+  (KeyCode::Tab, FrontendEvent::SelectNextAgent),
+  (KeyCode::KeyN, FrontendEvent::CycleSelectedAgentGroup),
+  (
+    KeyCode::Digit1,
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  ),
+  (
+    KeyCode::Digit2,
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkLeftRight),
+  ),
+  (
+    KeyCode::Digit3,
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::WalkClockwise),
+  ),
+  (
+    KeyCode::Digit4,
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  ),
+  (
+    KeyCode::Digit5,
+    FrontendEvent::SetBehaviour(MovementBehaviourKind::HungerBased),
+  ),
+  (
+    KeyCode::Digit6,
+    FrontendEvent::SetSpawnBehaviours(MovementBehaviourWeights::EVEN),
+  ),
+  (KeyCode::KeyF, FrontendEvent::SpawnFruitSpawner),
+  (KeyCode::KeyC, FrontendEvent::SpawnCoinSpawner),
+  (KeyCode::KeyH, FrontendEvent::EnableHunger),
+  (KeyCode::KeyW, FrontendEvent::SpawnWalls),
+  (KeyCode::KeyQ, FrontendEvent::ClearWalls),
+  (KeyCode::KeyT, FrontendEvent::ScatterTerrain),
+  (KeyCode::KeyY, FrontendEvent::ClearTerrain),
+  (KeyCode::KeyM, FrontendEvent::GenerateMaze),
+  (KeyCode::KeyR, FrontendEvent::GenerateRooms),
+  (KeyCode::KeyV, FrontendEvent::GenerateCaves),
+  (KeyCode::KeyB, FrontendEvent::ClearMap),
+  (KeyCode::KeyO, FrontendEvent::ToggleFogOfWar),
+  (KeyCode::KeyP, FrontendEvent::ToggleTreeVisualiser),
+  (KeyCode::KeyK, FrontendEvent::ToggleWrap),
+  (KeyCode::KeyD, FrontendEvent::ToggleDiagonalMovement),
+  (KeyCode::KeyE, FrontendEvent::CycleDistanceMetric),
+  (KeyCode::KeyG, FrontendEvent::ToggleHexGrid),
+  (KeyCode::KeyL, FrontendEvent::CycleResizePolicy),
+  (KeyCode::F1, FrontendEvent::SetWorldSize(100, 100)),
+  (KeyCode::F2, FrontendEvent::SetWorldSize(500, 500)),
+  (KeyCode::KeyZ, FrontendEvent::FitCameraToWorld),
+  (KeyCode::KeyX, FrontendEvent::ToggleCameraFollow),
+  (KeyCode::KeyI, FrontendEvent::CycleMovementEasing),
+  (KeyCode::Space, FrontendEvent::TogglePause),
+  (KeyCode::Period, FrontendEvent::StepSimulation),
+  (KeyCode::Digit7, FrontendEvent::SetSimulationSpeed(0.5)),
+  (KeyCode::Digit8, FrontendEvent::SetSimulationSpeed(1.0)),
+  (KeyCode::Digit9, FrontendEvent::SetSimulationSpeed(2.0)),
+  (KeyCode::Digit0, FrontendEvent::SetSimulationSpeed(4.0)),
+];
+
+/// (the behaviour shortcuts, but only for the selected agent)
+const SHIFT_SHORTCUTS: &[(KeyCode, FrontendEvent)] = &[
+  (
+    KeyCode::Digit1,
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  ),
+  (
+    KeyCode::Digit2,
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkLeftRight),
+  ),
+  (
+    KeyCode::Digit3,
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::WalkClockwise),
+  ),
+  (
+    KeyCode::Digit4,
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  ),
+  (
+    KeyCode::Digit5,
+    FrontendEvent::SetSelectedAgentBehaviour(MovementBehaviourKind::HungerBased),
+  ),
+];
+
+/// (the behaviour shortcuts, but only for the selected agent's group)
+const CONTROL_SHORTCUTS: &[(KeyCode, FrontendEvent)] = &[
+  (
+    KeyCode::Digit1,
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkLeftRightNaive),
+  ),
+  (
+    KeyCode::Digit2,
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkLeftRight),
+  ),
+  (
+    KeyCode::Digit3,
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::WalkClockwise),
+  ),
+  (
+    KeyCode::Digit4,
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::MoveToClosestFruit),
+  ),
+  (
+    KeyCode::Digit5,
+    FrontendEvent::SetSelectedGroupBehaviour(MovementBehaviourKind::HungerBased),
+  ),
+];
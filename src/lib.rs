@@ -1,36 +1,65 @@
 mod agent;
 mod behaviours;
+mod camera;
 mod coins;
+mod entropy;
 mod fruit;
 mod glue;
 mod grid;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
 mod hunger;
+mod inspector;
+mod interpolation;
+mod map_generation;
+mod memory;
+mod pathfinding;
 mod points;
 mod resizing;
 mod schedule;
+mod spatial_index;
+mod terrain;
+mod tree_visualiser;
+mod visuals;
+mod walls;
 
 use agent::SpawnAgent;
 use bevy::prelude::*;
 
-use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
-use wasm_bindgen::prelude::*;
+// (for the native binary, which adds its own keyboard frontend)
+pub use behaviours::{MovementBehaviourKind, MovementBehaviourWeights};
+pub use entropy::SimulationSeed;
+pub use glue::{FrontendEvent, configure_from_query};
 
-#[wasm_bindgen(start)]
-pub fn main() {
-  App::new()
-    .insert_resource(ClearColor(Color::srgba(0.0, 0.0, 0.0, 0.0)))
+/// Builds the app, to be shown in the given window. The frontends (the web page, or the native
+/// binary) only differ in how the window is set up, and in how the user interacts with the app:
+/// on the web through `glue`, natively through the binary's keyboard shortcuts.
+pub fn build_app(primary_window: Window) -> App {
+  let mut app = App::new();
+  app
     .add_plugins(DefaultPlugins.set(WindowPlugin {
-      // wire up the Bevy rendering window as the Canvas in the DOM
-      primary_window: Some(Window {
-        canvas: Some("#behave-demo-canvas".into()),
-        fit_canvas_to_parent: true,
-        ..default()
-      }),
+      primary_window: Some(primary_window),
       ..default()
     }))
-    .add_plugins(EntropyPlugin::<WyRand>::default())
-    // this app's plugins
-    .add_plugins(glue::glue_plugin)
+    .add_plugins(simulation_plugin)
+    // drawing the simulation, and interacting with it
+    .add_plugins(visuals::visuals_plugin)
+    .add_plugins(camera::camera_plugin)
+    .add_plugins(interpolation::interpolation_plugin)
+    .add_plugins(hunger::hunger_indicator_plugin)
+    .add_plugins(points::points_indicator_plugin)
+    .add_plugins(memory::fog_of_war_plugin)
+    .add_plugins(tree_visualiser::tree_visualiser_plugin)
+    .add_plugins(inspector::inspector_plugin);
+  #[cfg(target_arch = "wasm32")]
+  app.add_plugins(glue::web_plugin);
+  app
+}
+
+/// Everything that is simulated, without drawing it (so that it can also run headless).
+pub fn simulation_plugin(app: &mut App) {
+  app
+    .add_plugins(entropy::entropy_plugin)
     .add_plugins(schedule::schedule_plugin)
     .add_plugins(resizing::resizing_plugin)
     .add_plugins(grid::grid_plugin)
@@ -40,54 +69,164 @@ pub fn main() {
     .add_plugins(fruit::fruit_plugin)
     .add_plugins(coins::coins_plugin)
     .add_plugins(points::points_plugin)
-    // main systems & observers
-    .add_systems(Startup, setup)
-    .add_observer(on_web_event)
-    .run();
+    .add_plugins(walls::walls_plugin)
+    .add_plugins(terrain::terrain_plugin)
+    .add_plugins(map_generation::map_generation_plugin)
+    .add_plugins(spatial_index::spatial_index_plugin)
+    .add_observer(on_frontend_event);
 }
 
-fn setup(mut commands: Commands) {
-  commands.spawn(Camera2d);
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
+pub fn main() {
+  build_app(Window {
+    // wire up the Bevy rendering window as the Canvas in the DOM
+    canvas: Some("#behave-demo-canvas".into()),
+    fit_canvas_to_parent: true,
+    ..default()
+  })
+  // let the page show through
+  .insert_resource(ClearColor(Color::srgba(0.0, 0.0, 0.0, 0.0)))
+  .run();
 }
 
-fn on_web_event(trigger: Trigger<glue::WebEvent>, mut commands: Commands) {
+fn on_frontend_event(
+  trigger: Trigger<FrontendEvent>,
+  r_selected_agent: Res<agent::SelectedAgent>,
+  q_groups: Query<&agent::AgentGroup>,
+  mut commands: Commands,
+) {
   match trigger.event() {
-    glue::WebEvent::SpawnAgent => {
+    FrontendEvent::SpawnAgent => {
       commands.trigger(SpawnAgent);
     }
-    glue::WebEvent::SetBehaviourWalkLeftRightNaive => {
-      commands.trigger(behaviours::DisableNaiveMovementBehaviours);
-      commands.trigger(behaviours::DisableMovementBehaviours);
-      commands.trigger(behaviours::SetBehaviourWalkLeftRightNaive);
+    FrontendEvent::SetBehaviour(behaviour) => {
+      commands.trigger(behaviours::AssignMovementBehaviour {
+        behaviour: *behaviour,
+        to: behaviours::Assignees::All,
+      });
     }
-    glue::WebEvent::SetBehaviourWalkLeftRight => {
-      commands.trigger(behaviours::DisableNaiveMovementBehaviours);
-      commands.trigger(behaviours::DisableMovementBehaviours);
-      commands.trigger(behaviours::SetBehaviourWalkLeftRight);
+    FrontendEvent::SetSelectedAgentBehaviour(behaviour) => {
+      let Some(agent) = r_selected_agent.0 else {
+        info!("no agent selected to give a behaviour to");
+        return;
+      };
+      commands.trigger(behaviours::AssignMovementBehaviour {
+        behaviour: *behaviour,
+        to: behaviours::Assignees::Agent(agent),
+      });
     }
-    glue::WebEvent::SetBehaviourWalkClockwise => {
-      commands.trigger(behaviours::DisableNaiveMovementBehaviours);
-      commands.trigger(behaviours::DisableMovementBehaviours);
-      commands.trigger(behaviours::SetBehaviourWalkClockwise);
+    FrontendEvent::SetSelectedGroupBehaviour(behaviour) => {
+      let Some(group) = r_selected_agent
+        .0
+        .and_then(|agent| q_groups.get(agent).ok())
+      else {
+        info!("the selected agent is not in a group to give a behaviour to");
+        return;
+      };
+      commands.trigger(behaviours::AssignMovementBehaviour {
+        behaviour: *behaviour,
+        to: behaviours::Assignees::Group(*group),
+      });
     }
-    glue::WebEvent::SetBehaviourMoveToClosestFruit => {
-      commands.trigger(behaviours::DisableNaiveMovementBehaviours);
-      commands.trigger(behaviours::DisableMovementBehaviours);
-      commands.trigger(behaviours::SetBehaviourMoveToClosestFruit);
+    FrontendEvent::SetSpawnBehaviours(weights) => {
+      commands.trigger(behaviours::SetSpawnBehaviours(*weights));
     }
-    glue::WebEvent::SetBehaviourHungerBased => {
-      commands.trigger(behaviours::DisableNaiveMovementBehaviours);
-      commands.trigger(behaviours::DisableMovementBehaviours);
-      commands.trigger(behaviours::SetBehaviourHungerBased);
-    }
-    glue::WebEvent::SpawnFruitSpawner => {
+    FrontendEvent::SpawnFruitSpawner => {
       commands.trigger(fruit::SpawnFruitSpawner);
     }
-    glue::WebEvent::SpawnCoinSpawner => {
+    FrontendEvent::SpawnCoinSpawner => {
       commands.trigger(coins::SpawnCoinSpawner);
     }
-    glue::WebEvent::EnableHunger => {
+    FrontendEvent::EnableHunger => {
       commands.trigger(hunger::EnableHunger);
     }
+    FrontendEvent::SpawnWalls => {
+      commands.trigger(walls::SpawnWalls);
+    }
+    FrontendEvent::ClearWalls => {
+      commands.trigger(walls::ClearWalls);
+    }
+    FrontendEvent::ScatterTerrain => {
+      commands.trigger(terrain::ScatterTerrain);
+    }
+    FrontendEvent::ClearTerrain => {
+      commands.trigger(terrain::ClearTerrain);
+    }
+    FrontendEvent::GenerateMaze => {
+      commands.trigger(map_generation::GenerateMap(
+        map_generation::MapAlgorithm::Maze,
+      ));
+    }
+    FrontendEvent::GenerateRooms => {
+      commands.trigger(map_generation::GenerateMap(
+        map_generation::MapAlgorithm::Rooms,
+      ));
+    }
+    FrontendEvent::GenerateCaves => {
+      commands.trigger(map_generation::GenerateMap(
+        map_generation::MapAlgorithm::Caves,
+      ));
+    }
+    FrontendEvent::ClearMap => {
+      commands.trigger(map_generation::ClearMap);
+    }
+    FrontendEvent::SelectNextAgent => {
+      commands.trigger(agent::SelectNextAgent);
+    }
+    FrontendEvent::CycleSelectedAgentGroup => {
+      commands.trigger(agent::CycleSelectedAgentGroup);
+    }
+    FrontendEvent::ToggleFogOfWar => {
+      commands.trigger(memory::ToggleFogOfWar);
+    }
+    FrontendEvent::ToggleTreeVisualiser => {
+      commands.trigger(tree_visualiser::ToggleTreeVisualiser);
+    }
+    FrontendEvent::ToggleWrap => {
+      commands.trigger(grid::ToggleWrap);
+    }
+    FrontendEvent::ToggleDiagonalMovement => {
+      commands.trigger(grid::ToggleDiagonalMovement);
+    }
+    FrontendEvent::CycleDistanceMetric => {
+      commands.trigger(grid::CycleDistanceMetric);
+    }
+    FrontendEvent::ToggleHexGrid => {
+      commands.trigger(resizing::ToggleHexGrid);
+    }
+    FrontendEvent::CycleResizePolicy => {
+      commands.trigger(resizing::CycleResizePolicy);
+    }
+    FrontendEvent::SetWorldSize(width, height) => {
+      commands.trigger(resizing::SetWorldSize {
+        width: *width,
+        height: *height,
+      });
+    }
+    FrontendEvent::FitCameraToWorld => {
+      commands.trigger(camera::FitCameraToWorld);
+    }
+    FrontendEvent::ToggleCameraFollow => {
+      commands.trigger(camera::ToggleCameraFollow);
+    }
+    FrontendEvent::SetGridSizingConfig(config) => {
+      commands.trigger(resizing::SetGridSizingConfig(*config));
+    }
+    FrontendEvent::CycleMovementEasing => {
+      commands.trigger(interpolation::CycleMovementEasing);
+    }
+    FrontendEvent::TogglePause => {
+      commands.trigger(schedule::TogglePause);
+    }
+    FrontendEvent::StepSimulation => {
+      commands.trigger(schedule::StepSimulation);
+    }
+    FrontendEvent::SetSimulationSpeed(speed) => {
+      commands.trigger(schedule::SetSimulationSpeed(*speed));
+    }
+    FrontendEvent::SetSeed(seed) => {
+      commands.trigger(entropy::SetSeed(*seed));
+    }
   }
 }
@@ -0,0 +1,14 @@
+mod keyboard;
+
+use bevy::prelude::*;
+
+fn main() {
+  let mut app = behave_blog_demo::build_app(Window {
+    title: "Behave demo".into(),
+    ..default()
+  });
+  if let Some(query) = keyboard::startup_query() {
+    behave_blog_demo::configure_from_query(&mut app, &query);
+  }
+  app.add_plugins(keyboard::keyboard_plugin).run();
+}
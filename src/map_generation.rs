@@ -0,0 +1,464 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rand::prelude::WyRand;
+use rand::{Rng, seq::SliceRandom};
+use rand_core::{RngCore, SeedableRng};
+
+use crate::{
+  entropy::{MapGeneration, RngStream},
+  grid::{GridBounds, GridCell},
+  resizing::GridSizeChanged,
+  terrain::{Terrain, TerrainMap},
+  walls::ReplaceWalls,
+};
+
+pub fn map_generation_plugin(app: &mut App) {
+  app
+    .init_resource::<MapGenerator>()
+    .add_observer(on_generate_map)
+    .add_observer(on_clear_map)
+    .add_observer(regenerate_on_grid_size_changed)
+    .add_observer(regenerate_map);
+}
+
+fn on_generate_map(
+  trigger: Trigger<GenerateMap>,
+  mut r_map_generator: ResMut<MapGenerator>,
+  mut commands: Commands,
+  mut rng: ResMut<RngStream<MapGeneration>>,
+) {
+  // draw the map's seed from its own stream, so a seeded app generates the same maps
+  r_map_generator.algorithm = Some(trigger.event().0);
+  r_map_generator.seed = rng.next_u64();
+  commands.trigger(RegenerateMap);
+}
+
+fn on_clear_map(
+  _trigger: Trigger<ClearMap>,
+  mut r_map_generator: ResMut<MapGenerator>,
+  mut r_terrain_map: ResMut<TerrainMap>,
+  mut commands: Commands,
+) {
+  r_map_generator.algorithm = None;
+  *r_terrain_map = TerrainMap::default();
+  commands.trigger(ReplaceWalls(Vec::new()));
+}
+
+/// Regenerates the map for the new grid size, using the same seed as before.
+fn regenerate_on_grid_size_changed(
+  _trigger: Trigger<GridSizeChanged>,
+  r_map_generator: Res<MapGenerator>,
+  mut commands: Commands,
+) {
+  if r_map_generator.algorithm.is_some() {
+    commands.trigger(RegenerateMap);
+  }
+}
+
+fn regenerate_map(
+  _trigger: Trigger<RegenerateMap>,
+  r_map_generator: Res<MapGenerator>,
+  r_grid_bounds: Res<GridBounds>,
+  mut r_terrain_map: ResMut<TerrainMap>,
+  mut commands: Commands,
+) {
+  let Some(algorithm) = r_map_generator.algorithm else {
+    return;
+  };
+
+  let layout = MapLayout::generate(
+    algorithm,
+    r_grid_bounds.width(),
+    r_grid_bounds.height(),
+    r_map_generator.seed,
+  );
+
+  let mut walls = Vec::new();
+  let mut terrain = HashMap::new();
+  for x in 0..layout.width {
+    for y in 0..layout.height {
+      let cell = GridCell::new(
+        r_grid_bounds.left_inclusive() + x as isize,
+        r_grid_bounds.top_inclusive() + y as isize,
+      );
+      if layout.is_wall(x, y) {
+        walls.push(cell);
+      } else if layout.terrain(x, y) != Terrain::default() {
+        terrain.insert(cell, layout.terrain(x, y));
+      }
+    }
+  }
+
+  *r_terrain_map = TerrainMap::from(terrain);
+  commands.trigger(ReplaceWalls(walls));
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapAlgorithm {
+  /// A perfect maze, carved by a recursive backtracker.
+  Maze,
+  /// Rooms in a binary space partition, connected by roads.
+  Rooms,
+  /// Caves grown by a cellular automaton, with some muddy and wet patches.
+  Caves,
+}
+
+/// The currently selected map algorithm (if any), and the seed it generates the map with.
+#[derive(Resource, Default)]
+pub struct MapGenerator {
+  algorithm: Option<MapAlgorithm>,
+  seed: u64,
+}
+
+/// Walls and terrain for a grid of `width` by `height` cells, in coordinates relative to the
+/// grid's top left corner.
+struct MapLayout {
+  width: usize,
+  height: usize,
+  walls: Vec<bool>,
+  terrain: Vec<Terrain>,
+}
+
+impl MapLayout {
+  fn new(width: usize, height: usize, wall: bool) -> Self {
+    Self {
+      width,
+      height,
+      walls: vec![wall; width * height],
+      terrain: vec![Terrain::default(); width * height],
+    }
+  }
+
+  fn generate(algorithm: MapAlgorithm, width: usize, height: usize, seed: u64) -> Self {
+    let mut rng = WyRand::seed_from_u64(seed);
+    match algorithm {
+      MapAlgorithm::Maze => Self::generate_maze(width, height, &mut rng),
+      MapAlgorithm::Rooms => Self::generate_rooms(width, height, &mut rng),
+      MapAlgorithm::Caves => Self::generate_caves(width, height, &mut rng),
+    }
+  }
+
+  fn is_wall(&self, x: usize, y: usize) -> bool {
+    self.walls[y * self.width + x]
+  }
+
+  fn set_wall(&mut self, x: usize, y: usize, wall: bool) {
+    self.walls[y * self.width + x] = wall;
+  }
+
+  fn terrain(&self, x: usize, y: usize) -> Terrain {
+    self.terrain[y * self.width + x]
+  }
+
+  fn set_terrain(&mut self, x: usize, y: usize, terrain: Terrain) {
+    self.terrain[y * self.width + x] = terrain;
+  }
+
+  /// Counts the walls among the 8 cells around (x, y). Cells outside the layout count as walls.
+  fn count_wall_neighbours(&self, x: usize, y: usize) -> usize {
+    let mut count = 0;
+    for dx in -1..=1 {
+      for dy in -1..=1 {
+        if dx == 0 && dy == 0 {
+          continue;
+        }
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if nx < 0
+          || ny < 0
+          || nx >= self.width as isize
+          || ny >= self.height as isize
+          || self.is_wall(nx as usize, ny as usize)
+        {
+          count += 1;
+        }
+      }
+    }
+    count
+  }
+
+  /// Carves a maze between the cells with odd coordinates, leaving walls everywhere else.
+  fn generate_maze(width: usize, height: usize, rng: &mut WyRand) -> Self {
+    let (columns, rows) = (width.saturating_sub(1) / 2, height.saturating_sub(1) / 2);
+    if columns == 0 || rows == 0 {
+      // too small to fit a maze
+      return Self::new(width, height, false);
+    }
+
+    let mut layout = Self::new(width, height, true);
+    let mut visited = vec![false; columns * rows];
+    let start = (rng.gen_range(0..columns), rng.gen_range(0..rows));
+    visited[start.1 * columns + start.0] = true;
+    layout.set_wall(2 * start.0 + 1, 2 * start.1 + 1, false);
+
+    let mut stack = vec![start];
+    while let Some(&(x, y)) = stack.last() {
+      let options = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .into_iter()
+        .map(|(dx, dy)| (x as isize + dx, y as isize + dy))
+        .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < columns as isize && ny < rows as isize)
+        .map(|(nx, ny)| (nx as usize, ny as usize))
+        .filter(|&(nx, ny)| !visited[ny * columns + nx])
+        .collect::<Vec<_>>();
+
+      let Some(&(nx, ny)) = options.choose(rng) else {
+        // dead end, backtrack
+        stack.pop();
+        continue;
+      };
+
+      // knock down the wall between the two cells, and carve out the next one
+      layout.set_wall(x + nx + 1, y + ny + 1, false);
+      layout.set_wall(2 * nx + 1, 2 * ny + 1, false);
+      visited[ny * columns + nx] = true;
+      stack.push((nx, ny));
+    }
+
+    layout
+  }
+
+  /// Recursively splits the grid in two, places a room in every leaf, and connects sibling rooms
+  /// with roads.
+  fn generate_rooms(width: usize, height: usize, rng: &mut WyRand) -> Self {
+    let mut layout = Self::new(width, height, true);
+    if width == 0 || height == 0 {
+      // nothing to put rooms in
+      return layout;
+    }
+    layout.carve_partition(
+      Partition {
+        x: 0,
+        y: 0,
+        width,
+        height,
+      },
+      rng,
+    );
+    layout
+  }
+
+  /// Carves the rooms in this partition, and returns the center of one of them.
+  fn carve_partition(&mut self, partition: Partition, rng: &mut WyRand) -> (usize, usize) {
+    if let Some((a, b)) = partition.split(rng) {
+      let a_center = self.carve_partition(a, rng);
+      let b_center = self.carve_partition(b, rng);
+      self.carve_road(a_center, b_center, rng);
+      return if rng.gen_bool(0.5) {
+        a_center
+      } else {
+        b_center
+      };
+    }
+
+    let (x, room_width) = place_room(partition.x, partition.width, rng);
+    let (y, room_height) = place_room(partition.y, partition.height, rng);
+    let floor = if rng.gen_bool(0.2) {
+      Terrain::Mud
+    } else {
+      Terrain::Grass
+    };
+
+    for rx in x..x + room_width {
+      for ry in y..y + room_height {
+        self.set_wall(rx, ry, false);
+        self.set_terrain(rx, ry, floor);
+      }
+    }
+
+    (x + room_width / 2, y + room_height / 2)
+  }
+
+  /// Carves an L-shaped road between two points, without paving over any room floors.
+  fn carve_road(&mut self, from: (usize, usize), to: (usize, usize), rng: &mut WyRand) {
+    let corner = if rng.gen_bool(0.5) {
+      (to.0, from.1)
+    } else {
+      (from.0, to.1)
+    };
+    for (a, b) in [(from, corner), (corner, to)] {
+      for x in a.0.min(b.0)..=a.0.max(b.0) {
+        for y in a.1.min(b.1)..=a.1.max(b.1) {
+          if self.is_wall(x, y) {
+            self.set_wall(x, y, false);
+            self.set_terrain(x, y, Terrain::Road);
+          }
+        }
+      }
+    }
+  }
+
+  /// Grows caves from random noise, keeps only the largest cave, and makes some of it muddy or wet.
+  fn generate_caves(width: usize, height: usize, rng: &mut WyRand) -> Self {
+    let mut layout = Self::new(width, height, false);
+    layout.walls = (0..width * height)
+      .map(|_| rng.gen_bool(CAVE_WALL_CHANCE))
+      .collect();
+    layout.smooth(CAVE_SMOOTHING_ITERATIONS);
+    layout.fill_all_but_largest_cave();
+
+    // reuse the same automaton on a fresh noise layer to grow wet patches
+    let mut wetness = Self::new(width, height, false);
+    wetness.walls = (0..width * height)
+      .map(|_| rng.gen_bool(CAVE_WET_CHANCE))
+      .collect();
+    wetness.smooth(CAVE_SMOOTHING_ITERATIONS);
+
+    for x in 0..width {
+      for y in 0..height {
+        if layout.is_wall(x, y) || !wetness.is_wall(x, y) {
+          continue;
+        }
+        // the middle of a wet patch is water, its edges are mud
+        let terrain = if wetness.count_wall_neighbours(x, y) == 8 {
+          Terrain::Water
+        } else {
+          Terrain::Mud
+        };
+        layout.set_terrain(x, y, terrain);
+      }
+    }
+
+    layout
+  }
+
+  /// Runs the 4-5 cellular automaton rule: cells with many neighbouring walls become walls, and
+  /// cells with few become open.
+  fn smooth(&mut self, iterations: usize) {
+    for _ in 0..iterations {
+      let mut walls = self.walls.clone();
+      for x in 0..self.width {
+        for y in 0..self.height {
+          match self.count_wall_neighbours(x, y) {
+            n if n > 4 => walls[y * self.width + x] = true,
+            n if n < 4 => walls[y * self.width + x] = false,
+            _ => {}
+          }
+        }
+      }
+      self.walls = walls;
+    }
+  }
+
+  /// Fills in every cave except the largest one, so all open cells are reachable from each other.
+  fn fill_all_but_largest_cave(&mut self) {
+    let mut cave_ids = vec![None; self.width * self.height];
+    let mut cave_sizes = Vec::new();
+
+    for start in 0..self.walls.len() {
+      if self.walls[start] || cave_ids[start].is_some() {
+        continue;
+      }
+      let id = cave_sizes.len();
+      let mut size = 0;
+      let mut stack = vec![start];
+      cave_ids[start] = Some(id);
+      while let Some(i) = stack.pop() {
+        size += 1;
+        let (x, y) = (i % self.width, i / self.width);
+        let mut neighbours = Vec::with_capacity(4);
+        if x > 0 {
+          neighbours.push(i - 1);
+        }
+        if x + 1 < self.width {
+          neighbours.push(i + 1);
+        }
+        if y > 0 {
+          neighbours.push(i - self.width);
+        }
+        if y + 1 < self.height {
+          neighbours.push(i + self.width);
+        }
+        for n in neighbours {
+          if !self.walls[n] && cave_ids[n].is_none() {
+            cave_ids[n] = Some(id);
+            stack.push(n);
+          }
+        }
+      }
+      cave_sizes.push(size);
+    }
+
+    let Some(largest) = (0..cave_sizes.len()).max_by_key(|&id| cave_sizes[id]) else {
+      // no caves at all, so open everything up rather than leaving a solid block
+      self.walls.fill(false);
+      return;
+    };
+    for (i, cave_id) in cave_ids.into_iter().enumerate() {
+      if cave_id.is_some_and(|id| id != largest) {
+        self.walls[i] = true;
+      }
+    }
+  }
+}
+
+const MIN_PARTITION_SIZE: usize = 6;
+const MIN_ROOM_SIZE: usize = 3;
+const CAVE_WALL_CHANCE: f64 = 0.45;
+const CAVE_WET_CHANCE: f64 = 0.4;
+const CAVE_SMOOTHING_ITERATIONS: usize = 5;
+
+/// Picks where a room goes along one side of a (non-empty) partition, and returns where the room
+/// starts and how long it is. A margin of one wall is left on both ends if it fits, so that rooms
+/// in neighbouring partitions don't merge.
+fn place_room(start: usize, size: usize, rng: &mut WyRand) -> (usize, usize) {
+  let margin = if size > 2 { 1 } else { 0 };
+  let max_room_size = size - 2 * margin;
+  let room_size = rng.gen_range(MIN_ROOM_SIZE.min(max_room_size)..=max_room_size);
+  let offset = rng.gen_range(margin..=size - margin - room_size);
+  (start + offset, room_size)
+}
+
+#[derive(Clone, Copy)]
+struct Partition {
+  x: usize,
+  y: usize,
+  width: usize,
+  height: usize,
+}
+
+impl Partition {
+  /// Splits the partition in two along its longest side, unless it's too small to split.
+  fn split(&self, rng: &mut WyRand) -> Option<(Partition, Partition)> {
+    let split_horizontally = self.width >= self.height;
+    let size = if split_horizontally {
+      self.width
+    } else {
+      self.height
+    };
+    if size < 2 * MIN_PARTITION_SIZE {
+      return None;
+    }
+
+    let at = rng.gen_range(MIN_PARTITION_SIZE..=size - MIN_PARTITION_SIZE);
+    Some(if split_horizontally {
+      (
+        Partition { width: at, ..*self },
+        Partition {
+          x: self.x + at,
+          width: self.width - at,
+          ..*self
+        },
+      )
+    } else {
+      (
+        Partition {
+          height: at,
+          ..*self
+        },
+        Partition {
+          y: self.y + at,
+          height: self.height - at,
+          ..*self
+        },
+      )
+    })
+  }
+}
+
+#[derive(Event)]
+pub struct GenerateMap(pub MapAlgorithm);
+
+#[derive(Event)]
+pub struct ClearMap;
+
+#[derive(Event)]
+struct RegenerateMap;
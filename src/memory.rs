@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::{
+  agent::SelectedAgent,
+  behaviours::TargetKind,
+  grid::{GridBounds, GridCell, Ground},
+  visuals::Visuals,
+};
+
+/// Hides the cells that the selected agent has never seen.
+pub fn fog_of_war_plugin(app: &mut App) {
+  app
+    .init_resource::<FogOfWarEnabled>()
+    .add_systems(
+      Update,
+      (insert_fog_on_ground_spawn, update_fog_of_war).chain(),
+    )
+    .add_observer(on_toggle_fog_of_war);
+}
+
+fn on_toggle_fog_of_war(
+  _trigger: Trigger<ToggleFogOfWar>,
+  mut r_fog_of_war_enabled: ResMut<FogOfWarEnabled>,
+) {
+  r_fog_of_war_enabled.0 = !r_fog_of_war_enabled.0;
+}
+
+fn insert_fog_on_ground_spawn(
+  q_new_ground: Query<Entity, Added<Ground>>,
+  r_grid_bounds: Res<GridBounds>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  let mesh = r_visuals.cell_mesh(&r_grid_bounds);
+  for ground in q_new_ground.iter() {
+    commands.entity(ground).with_child((
+      Fog,
+      Mesh2d(mesh.clone()),
+      MeshMaterial2d(r_visuals.fog_material.clone()),
+      // above the items and agents on the cell
+      Transform::from_xyz(0.0, 0.0, 0.5),
+      Visibility::Hidden,
+    ));
+  }
+}
+
+/// Shows fog on the cells that the selected agent has never seen.
+fn update_fog_of_war(
+  mut q_fog: Query<(&Parent, &mut Visibility), With<Fog>>,
+  q_added_fog: Query<(), Added<Fog>>,
+  q_ground: Query<&GridCell, With<Ground>>,
+  q_memories: Query<Ref<AgentMemory>>,
+  r_fog_of_war_enabled: Res<FogOfWarEnabled>,
+  r_selected_agent: Res<SelectedAgent>,
+) {
+  let memory = r_selected_agent
+    .0
+    .and_then(|agent| q_memories.get(agent).ok());
+
+  let memory_changed = memory.as_ref().is_some_and(|m| m.is_changed());
+  if !(memory_changed
+    || r_fog_of_war_enabled.is_changed()
+    || r_selected_agent.is_changed()
+    || !q_added_fog.is_empty())
+  {
+    return;
+  }
+
+  for (parent, mut visibility) in q_fog.iter_mut() {
+    let foggy = r_fog_of_war_enabled.0
+      && match (&memory, q_ground.get(parent.get())) {
+        (Some(memory), Ok(cell)) => !memory.has_seen(cell),
+        _ => false,
+      };
+    visibility.set_if_neq(if foggy {
+      Visibility::Inherited
+    } else {
+      Visibility::Hidden
+    });
+  }
+}
+
+/// Which cells an agent has seen, and which items it saw there when it last looked.
+#[derive(Component, Default)]
+pub struct AgentMemory {
+  seen: HashSet<GridCell>,
+  // (iterated when recalling items, so it needs a stable order)
+  items: BTreeMap<Entity, (GridCell, TargetKind)>,
+}
+
+impl AgentMemory {
+  /// Updates the memory with what is currently in view. Items that used to be on a visible cell,
+  /// but are not there anymore, are forgotten.
+  pub fn look(
+    &mut self,
+    visible_cells: BTreeSet<GridCell>,
+    visible_items: impl IntoIterator<Item = (Entity, GridCell, TargetKind)>,
+  ) {
+    self
+      .items
+      .retain(|_, (cell, _)| !visible_cells.contains(cell));
+    for (item, cell, kind) in visible_items {
+      self.items.insert(item, (cell, kind));
+    }
+    self.seen.extend(visible_cells);
+  }
+
+  /// Marks the visible cells as seen, without remembering the items on them.
+  pub fn see(&mut self, visible_cells: BTreeSet<GridCell>) {
+    self.seen.extend(visible_cells);
+  }
+
+  pub fn has_seen(&self, grid_cell: &GridCell) -> bool {
+    self.seen.contains(grid_cell)
+  }
+
+  /// The items of this kind that we remember, and where we saw them.
+  pub fn remembered(&self, kind: TargetKind) -> impl Iterator<Item = (Entity, &GridCell)> {
+    self
+      .items
+      .iter()
+      .filter(move |(_, (_, k))| *k == kind)
+      .map(|(item, (cell, _))| (*item, cell))
+  }
+}
+
+#[derive(Component)]
+struct Fog;
+
+#[derive(Resource, Default)]
+pub struct FogOfWarEnabled(pub bool);
+
+#[derive(Event)]
+pub struct ToggleFogOfWar;
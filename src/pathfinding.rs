@@ -0,0 +1,133 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+  grid::{GridBounds, GridCell},
+  walls::Walkability,
+};
+
+/// A cached route towards `goal`, as computed by [`find_path`].
+///
+/// The route excludes the cell the agent was standing on when it was computed, so the front of
+/// `steps` is always the next cell to move to.
+#[derive(Component, Debug)]
+pub struct Path {
+  goal: GridCell,
+  steps: VecDeque<GridCell>,
+}
+
+impl Path {
+  pub fn next_step(&self) -> Option<&GridCell> {
+    self.steps.front()
+  }
+
+  pub fn advance(&mut self) -> Option<GridCell> {
+    self.steps.pop_front()
+  }
+
+  /// Whether the agent at `from` can keep following this path towards `goal`.
+  pub fn is_valid_for(&self, from: &GridCell, goal: &GridCell, walkability: &Walkability) -> bool {
+    if self.goal != *goal {
+      // the target moved, so this path leads to the wrong place
+      return false;
+    }
+    let Some(next_step) = self.next_step() else {
+      return false;
+    };
+    // the next step must still be adjacent (we may have been moved) and walkable (it may be blocked)
+    walkability
+      .grid_bounds()
+      .neighbours(from)
+      .contains(next_step)
+      && walkability.can_step(from, next_step)
+  }
+}
+
+/// Finds the cheapest path from `from` to `to` using A*, only stepping on walkable cells. The cost
+/// of a step is the movement cost of the terrain that is stepped off.
+pub fn find_path(from: &GridCell, to: &GridCell, walkability: &Walkability) -> Option<Path> {
+  if !walkability.is_walkable(to) {
+    return None;
+  }
+  let r_grid_bounds = walkability.grid_bounds();
+
+  let mut open = BinaryHeap::new();
+  let mut came_from = HashMap::new();
+  let mut cost_so_far = HashMap::new();
+
+  open.push(Reverse((
+    r_grid_bounds.steps(from, to),
+    0,
+    <(isize, isize)>::from(from),
+  )));
+  cost_so_far.insert(*from, 0);
+
+  while let Some(Reverse((_, cost, (x, y)))) = open.pop() {
+    let current = GridCell::new(x, y);
+    if current == *to {
+      return Some(reconstruct_path(&came_from, from, to));
+    }
+    if cost > cost_so_far[&current] {
+      // we already found a cheaper way to this cell, this entry is stale
+      continue;
+    }
+
+    for next in r_grid_bounds.neighbours(&current) {
+      if !walkability.can_step(&current, &next) {
+        continue;
+      }
+      let next_cost = cost + walkability.terrain(&current).movement_cost();
+      if cost_so_far.get(&next).is_none_or(|&c| next_cost < c) {
+        cost_so_far.insert(next, next_cost);
+        came_from.insert(next, current);
+        open.push(Reverse((
+          next_cost + r_grid_bounds.steps(&next, to),
+          next_cost,
+          (&next).into(),
+        )));
+      }
+    }
+  }
+
+  None
+}
+
+fn reconstruct_path(
+  came_from: &HashMap<GridCell, GridCell>,
+  from: &GridCell,
+  to: &GridCell,
+) -> Path {
+  let mut steps = VecDeque::new();
+  let mut current = *to;
+  while current != *from {
+    steps.push_front(current);
+    current = came_from[&current];
+  }
+  Path { goal: *to, steps }
+}
+
+/// Finds the cell closest to `from` (in steps) for which `is_free` holds, using a breadth-first
+/// search that stays within the grid bounds.
+pub fn find_nearest(
+  from: &GridCell,
+  r_grid_bounds: &GridBounds,
+  is_free: impl Fn(&GridCell) -> bool,
+) -> Option<GridCell> {
+  let mut visited = HashSet::from([*from]);
+  let mut queue = VecDeque::from([*from]);
+
+  while let Some(current) = queue.pop_front() {
+    if is_free(&current) {
+      return Some(current);
+    }
+    for next in r_grid_bounds.neighbours(&current) {
+      if r_grid_bounds.contains(&next) && visited.insert(next) {
+        queue.push_back(next);
+      }
+    }
+  }
+
+  None
+}
@@ -1,17 +1,21 @@
-use bevy::color::palettes::tailwind as tw;
 use bevy::prelude::*;
 
 use crate::agent::Agent;
+use crate::schedule::{PrepareTickSet, SimulationTick};
+use crate::visuals::Visuals;
 
 pub fn points_plugin(app: &mut App) {
+  app.add_systems(
+    SimulationTick,
+    insert_points_on_agent_spawn.in_set(PrepareTickSet::Points),
+  );
+}
+
+/// Shows how close every agent is to its points goal, as a bar next to it.
+pub fn points_indicator_plugin(app: &mut App) {
   app.add_systems(
     Update,
-    (
-      insert_points_on_agent_spawn,
-      insert_indicator_on_points_spawn,
-      update_points_indicators,
-    )
-      .chain(),
+    (insert_indicator_on_points_spawn, update_points_indicators).chain(),
   );
 }
 
@@ -25,16 +29,14 @@ fn insert_points_on_agent_spawn(q_new_agents: Query<Entity, Added<Agent>>, mut c
 
 fn insert_indicator_on_points_spawn(
   q_agents_with_points: Query<Entity, Added<Points>>,
-  mut r_meshes: ResMut<Assets<Mesh>>,
-  mut r_materials: ResMut<Assets<ColorMaterial>>,
+  r_visuals: Res<Visuals>,
   mut commands: Commands,
 ) {
   for agent in q_agents_with_points.iter() {
     commands.entity(agent).with_child((
       PointsIndicator,
-      MeshMaterial2d(r_materials.add(Color::from(tw::YELLOW_400))),
-      // mesh is translated so that it scales from the side rather than from the center
-      Mesh2d(r_meshes.add(Mesh::from(Rectangle::new(0.15, 0.8)).translated_by(Vec3::Y * 0.4))),
+      MeshMaterial2d(r_visuals.points_material.clone()),
+      Mesh2d(r_visuals.indicator_mesh.clone()),
       Transform::from_xyz(0.3, -0.4, 0.1),
     ));
   }
@@ -67,6 +69,14 @@ impl Points {
     Self { current: 0, goal }
   }
 
+  pub fn current(&self) -> usize {
+    self.current
+  }
+
+  pub fn goal(&self) -> usize {
+    self.goal
+  }
+
   pub fn earn(&mut self, monetary_value: usize) {
     self.current = (self.current + monetary_value).clamp(0, self.goal);
   }
@@ -1,71 +1,462 @@
 use bevy::{prelude::*, window::WindowResized};
 
-use crate::grid::{CellSize, GridBounds};
+use crate::grid::{CellSize, GridBounds, GridCell, HEX_ROW_SPACING, Neighbourhood};
 
 pub fn resizing_plugin(app: &mut App) {
-  app.add_systems(Update, resize_grid_on_resize);
+  app
+    .init_resource::<ResizePolicy>()
+    .init_resource::<GridSizingConfig>()
+    .add_systems(Update, resize_grid_on_resize)
+    .add_observer(set_grid_sizing_config)
+    .add_observer(toggle_hex_grid)
+    .add_observer(cycle_resize_policy)
+    .add_observer(set_world_size)
+    .add_observer(log_despawned_on_resize);
 }
 
 fn resize_grid_on_resize(
   mut resize_reader: EventReader<WindowResized>,
+  r_resize_policy: Res<ResizePolicy>,
+  r_grid_sizing_config: Res<GridSizingConfig>,
   mut r_grid_bounds: ResMut<GridBounds>,
   mut r_cell_size: ResMut<CellSize>,
   mut commands: Commands,
 ) {
   for e in resize_reader.read() {
-    let (grid_bounds, cell_size) =
-      compute_grid_bounds_for_available_space(e.width.round() as usize, e.height.round() as usize);
-    if *r_grid_bounds != grid_bounds {
-      *r_grid_bounds = grid_bounds;
-      *r_cell_size = cell_size;
-      commands.trigger(GridSizeChanged);
-      // GridSizeChanged observer also triggers CellSizeChanged, so no need to trigger it here.
-    } else if *r_cell_size != cell_size {
-      *r_cell_size = cell_size;
-      commands.trigger(CellSizeChanged);
+    let (grid_bounds, cell_size) = compute_grid_bounds_for(
+      &r_grid_bounds,
+      &r_resize_policy,
+      &r_grid_sizing_config,
+      e.width.round() as usize,
+      e.height.round() as usize,
+    );
+    update_grid_size(
+      grid_bounds,
+      cell_size,
+      &mut r_grid_bounds,
+      &mut r_cell_size,
+      &mut commands,
+    );
+  }
+}
+
+/// Applies a new grid sizing config, and refits the grid to the window with it.
+fn set_grid_sizing_config(
+  trigger: Trigger<SetGridSizingConfig>,
+  q_windows: Query<&Window>,
+  r_resize_policy: Res<ResizePolicy>,
+  mut r_grid_sizing_config: ResMut<GridSizingConfig>,
+  mut r_grid_bounds: ResMut<GridBounds>,
+  mut r_cell_size: ResMut<CellSize>,
+  mut commands: Commands,
+) {
+  *r_grid_sizing_config = trigger.event().0;
+
+  let Ok(window) = q_windows.get_single() else {
+    return;
+  };
+  let (grid_bounds, cell_size) = compute_grid_bounds_for(
+    &r_grid_bounds,
+    &r_resize_policy,
+    &r_grid_sizing_config,
+    window.width().round() as usize,
+    window.height().round() as usize,
+  );
+  update_grid_size(
+    grid_bounds,
+    cell_size,
+    &mut r_grid_bounds,
+    &mut r_cell_size,
+    &mut commands,
+  );
+}
+
+fn update_grid_size(
+  grid_bounds: GridBounds,
+  cell_size: CellSize,
+  r_grid_bounds: &mut GridBounds,
+  r_cell_size: &mut CellSize,
+  commands: &mut Commands,
+) {
+  if *r_grid_bounds != grid_bounds {
+    *r_grid_bounds = grid_bounds;
+    *r_cell_size = cell_size;
+    commands.trigger(GridSizeChanged);
+    // GridSizeChanged observer also triggers CellSizeChanged, so no need to trigger it here.
+  } else if *r_cell_size != cell_size {
+    *r_cell_size = cell_size;
+    commands.trigger(CellSizeChanged);
+  }
+}
+
+/// Switches between square and hexagonal cells, which also changes how many cells fit on screen.
+fn toggle_hex_grid(
+  _trigger: Trigger<ToggleHexGrid>,
+  q_windows: Query<&Window>,
+  r_resize_policy: Res<ResizePolicy>,
+  r_grid_sizing_config: Res<GridSizingConfig>,
+  mut r_grid_bounds: ResMut<GridBounds>,
+  mut r_cell_size: ResMut<CellSize>,
+  mut commands: Commands,
+) {
+  let neighbourhood = match r_grid_bounds.neighbourhood() {
+    Neighbourhood::Hexagonal => Neighbourhood::VonNeumann,
+    Neighbourhood::VonNeumann | Neighbourhood::Moore => Neighbourhood::Hexagonal,
+  };
+  r_grid_bounds.set_neighbourhood(neighbourhood);
+
+  if let Ok(window) = q_windows.get_single() {
+    let (grid_bounds, cell_size) = compute_grid_bounds_for(
+      &r_grid_bounds,
+      &r_resize_policy,
+      &r_grid_sizing_config,
+      window.width().round() as usize,
+      window.height().round() as usize,
+    );
+    *r_grid_bounds = grid_bounds;
+    *r_cell_size = cell_size;
+  }
+  // even if the size stays the same, the cells need to be laid out (and shaped) differently
+  commands.trigger(GridSizeChanged);
+}
+
+fn cycle_resize_policy(
+  _trigger: Trigger<CycleResizePolicy>,
+  mut r_resize_policy: ResMut<ResizePolicy>,
+) {
+  *r_resize_policy = match *r_resize_policy {
+    ResizePolicy::Clamp => ResizePolicy::Relocate,
+    ResizePolicy::Relocate => ResizePolicy::Despawn,
+    ResizePolicy::Despawn => ResizePolicy::FixedWorld,
+    ResizePolicy::FixedWorld => ResizePolicy::Clamp,
+  };
+  info!("resize policy: {:?}", *r_resize_policy);
+}
+
+/// Gives the world a fixed size, independent of the size of the window.
+fn set_world_size(
+  trigger: Trigger<SetWorldSize>,
+  q_windows: Query<&Window>,
+  mut r_resize_policy: ResMut<ResizePolicy>,
+  mut r_grid_bounds: ResMut<GridBounds>,
+  mut r_cell_size: ResMut<CellSize>,
+  mut commands: Commands,
+) {
+  let SetWorldSize { width, height } = *trigger.event();
+  // (a world without cells has nowhere to put anything)
+  let (width, height) = (width.max(1), height.max(1));
+  *r_resize_policy = ResizePolicy::FixedWorld;
+  *r_grid_bounds = GridBounds::from_size(width, height).with_rules_of(&r_grid_bounds);
+  if let Ok(window) = q_windows.get_single() {
+    *r_cell_size = compute_cell_size_for_grid_bounds(
+      &r_grid_bounds,
+      window.width().round() as usize,
+      window.height().round() as usize,
+    );
+  }
+  commands.trigger(GridSizeChanged);
+}
+
+fn log_despawned_on_resize(trigger: Trigger<DespawnedOnResize>) {
+  let event = trigger.event();
+  info!(
+    "despawned {} at {:?}, which is outside the resized grid",
+    event.entity, event.cell
+  );
+}
+
+/// Computes the grid bounds (keeping the rules of the current ones) that fit the available space.
+fn compute_grid_bounds_for(
+  r_grid_bounds: &GridBounds,
+  r_resize_policy: &ResizePolicy,
+  r_grid_sizing_config: &GridSizingConfig,
+  width: usize,
+  height: usize,
+) -> (GridBounds, CellSize) {
+  if *r_resize_policy == ResizePolicy::FixedWorld && r_grid_bounds.width() > 0 {
+    // keep the world as it is, and only scale the cells to fit it in the available space
+    let cell_size = compute_cell_size_for_grid_bounds(r_grid_bounds, width, height);
+    return (r_grid_bounds.clone(), cell_size);
+  }
+
+  let (grid_bounds, cell_size) = match r_grid_bounds.neighbourhood() {
+    Neighbourhood::Hexagonal => {
+      compute_hex_grid_bounds_for_available_space(width, height, r_grid_sizing_config)
+    }
+    Neighbourhood::VonNeumann | Neighbourhood::Moore => {
+      compute_grid_bounds_for_available_space(width, height, r_grid_sizing_config)
+    }
+  };
+  (grid_bounds.with_rules_of(r_grid_bounds), cell_size)
+}
+
+/// What happens to the world when the window is resized.
+#[derive(Resource, Default, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ResizePolicy {
+  /// Agents and items outside the new bounds are moved onto the nearest edge.
+  Clamp,
+  /// Agents and items outside the new bounds are moved to the nearest free cell.
+  #[default]
+  Relocate,
+  /// Agents and items outside the new bounds are despawned (see [`DespawnedOnResize`]).
+  Despawn,
+  /// The grid keeps its size, and the cells are scaled to fit the window instead.
+  FixedWorld,
+}
+
+/// How the grid is sized to fit the window (when the [`ResizePolicy`] lets it change size).
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GridSizingConfig {
+  /// The size (in pixels) cells get, if there is room for enough of them.
+  pub cell_size_ideal: usize,
+  /// Cells shrink below the ideal size to fit at least this many columns.
+  pub min_horizontal_cells: usize,
+  /// Cells shrink below the ideal size to fit at least this many rows.
+  pub min_vertical_cells: usize,
+  pub mode: GridSizingMode,
+}
+
+impl Default for GridSizingConfig {
+  fn default() -> Self {
+    Self {
+      cell_size_ideal: 50,
+      min_horizontal_cells: 10,
+      min_vertical_cells: 10,
+      mode: GridSizingMode::FitIdealCellSize,
     }
   }
 }
 
+impl GridSizingConfig {
+  /// Reads the config from `key=value` pairs separated by `&` (like a URL query string), starting
+  /// from the defaults. Unrelated keys are ignored, so the whole query string of a page can be
+  /// passed in. Returns `None` if none of the keys are about grid sizing.
+  ///
+  /// The keys are `cell-size`, `min-columns`, `min-rows` and `sizing`, which is one of `fit`
+  /// (the default), `fixed-cell-size`, `fixed-cell-count` (using `columns` and `rows`) or
+  /// `fixed-aspect-ratio` (using `aspect-ratio`, e.g. `16:9` or `1.5`).
+  pub fn from_query(query: &str) -> Option<Self> {
+    let mut config = Self::default();
+    let (mut sizing, mut columns, mut rows, mut aspect_ratio) = (None, 20, 20, 1.0);
+    let mut any_keys = false;
+
+    let pairs = query
+      .trim_start_matches('?')
+      .split('&')
+      .filter_map(|pair| pair.split_once('='));
+    for (key, value) in pairs {
+      let parsed = match key {
+        "cell-size" => value.parse().map(|v| config.cell_size_ideal = v).is_ok(),
+        "min-columns" => value
+          .parse()
+          .map(|v| config.min_horizontal_cells = v)
+          .is_ok(),
+        "min-rows" => value.parse().map(|v| config.min_vertical_cells = v).is_ok(),
+        "columns" => value.parse().map(|v| columns = v).is_ok(),
+        "rows" => value.parse().map(|v| rows = v).is_ok(),
+        "aspect-ratio" => parse_aspect_ratio(value)
+          .map(|v| aspect_ratio = v)
+          .is_some(),
+        "sizing" => {
+          sizing = Some(value);
+          true
+        }
+        _ => continue,
+      };
+      if !parsed {
+        warn!("ignoring invalid grid sizing value {}={}", key, value);
+      }
+      any_keys = true;
+    }
+
+    config.mode = match sizing {
+      None | Some("fit") => GridSizingMode::FitIdealCellSize,
+      Some("fixed-cell-size") => GridSizingMode::FixedCellSize,
+      Some("fixed-cell-count") => GridSizingMode::FixedCellCount { columns, rows },
+      Some("fixed-aspect-ratio") => GridSizingMode::FixedAspectRatio(aspect_ratio),
+      Some(other) => {
+        warn!(
+          "unknown grid sizing mode {}, fitting ideal cells instead",
+          other
+        );
+        GridSizingMode::FitIdealCellSize
+      }
+    };
+    any_keys.then_some(config)
+  }
+}
+
+fn parse_aspect_ratio(value: &str) -> Option<f32> {
+  let aspect_ratio = match value.split_once(':') {
+    Some((width, height)) => width.parse::<f32>().ok()? / height.parse::<f32>().ok()?,
+    None => value.parse().ok()?,
+  };
+  (aspect_ratio.is_finite() && aspect_ratio > 0.0).then_some(aspect_ratio)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GridSizingMode {
+  /// As many cells of the ideal size as fit, but no fewer than the minimum number of cells.
+  FitIdealCellSize,
+  /// As many cells of the ideal size as fit, even if that's very few.
+  FixedCellSize,
+  /// Exactly this many cells, as large as fit.
+  FixedCellCount { columns: usize, rows: usize },
+  /// Like [`GridSizingMode::FitIdealCellSize`], but within the largest area of this aspect ratio
+  /// (width / height) that fits.
+  FixedAspectRatio(f32),
+}
+
 #[derive(Event)]
 pub struct GridSizeChanged;
 #[derive(Event)]
 pub struct CellSizeChanged;
+#[derive(Event)]
+pub struct ToggleHexGrid;
+#[derive(Event)]
+pub struct CycleResizePolicy;
+#[derive(Event)]
+pub struct SetGridSizingConfig(pub GridSizingConfig);
+#[derive(Event, Clone, Copy)]
+pub struct SetWorldSize {
+  pub width: usize,
+  pub height: usize,
+}
+
+/// An agent or item was despawned because it ended up outside the grid after a resize.
+#[derive(Event)]
+pub struct DespawnedOnResize {
+  pub entity: Entity,
+  pub cell: GridCell,
+}
+
+const MIN_CELL_SIZE: usize = 10;
 
-const CELL_SIZE_IDEAL: usize = 50;
-const MIN_HORIZONTAL_CELLS: usize = 10;
-const MIN_VERTICAL_CELLS: usize = 10;
+fn compute_grid_bounds_for_available_space(
+  width: usize,
+  height: usize,
+  r_grid_sizing_config: &GridSizingConfig,
+) -> (GridBounds, CellSize) {
+  let GridSizingConfig {
+    cell_size_ideal,
+    min_horizontal_cells,
+    min_vertical_cells,
+    mode,
+  } = *r_grid_sizing_config;
+  let cell_size_ideal = cell_size_ideal.max(1);
+
+  match mode {
+    GridSizingMode::FitIdealCellSize => {}
+    GridSizingMode::FixedCellSize => {
+      return (
+        GridBounds::from_size(
+          (width / cell_size_ideal).max(1),
+          (height / cell_size_ideal).max(1),
+        ),
+        CellSize(cell_size_ideal as f32),
+      );
+    }
+    GridSizingMode::FixedCellCount { columns, rows } => {
+      let grid_bounds = GridBounds::from_size(columns.max(1), rows.max(1));
+      let cell_size = (width / grid_bounds.width())
+        .min(height / grid_bounds.height())
+        .max(1);
+      return (grid_bounds, CellSize(cell_size as f32));
+    }
+    GridSizingMode::FixedAspectRatio(aspect_ratio) if aspect_ratio > 0.0 => {
+      // fit the ideal cells into the largest area with the right aspect ratio
+      let (width, height) = if width as f32 / height.max(1) as f32 > aspect_ratio {
+        ((height as f32 * aspect_ratio) as usize, height)
+      } else {
+        (width, (width as f32 / aspect_ratio) as usize)
+      };
+      let config = GridSizingConfig {
+        mode: GridSizingMode::FitIdealCellSize,
+        ..*r_grid_sizing_config
+      };
+      return compute_grid_bounds_for_available_space(width, height, &config);
+    }
+    GridSizingMode::FixedAspectRatio(_) => {}
+  }
 
-fn compute_grid_bounds_for_available_space(width: usize, height: usize) -> (GridBounds, CellSize) {
-  let n_horizontal_cells_using_ideal = width / CELL_SIZE_IDEAL;
-  let n_vertical_cells_using_ideal = height / CELL_SIZE_IDEAL;
+  let min_horizontal_cells = min_horizontal_cells.max(1);
+  let min_vertical_cells = min_vertical_cells.max(1);
+  let n_horizontal_cells_using_ideal = width / cell_size_ideal;
+  let n_vertical_cells_using_ideal = height / cell_size_ideal;
 
-  if n_horizontal_cells_using_ideal < MIN_HORIZONTAL_CELLS
-    || n_vertical_cells_using_ideal < MIN_VERTICAL_CELLS
+  if n_horizontal_cells_using_ideal < min_horizontal_cells
+    || n_vertical_cells_using_ideal < min_vertical_cells
   {
-    if n_horizontal_cells_using_ideal as f32 / (MIN_HORIZONTAL_CELLS as f32)
-      < n_vertical_cells_using_ideal as f32 / (MIN_VERTICAL_CELLS as f32)
+    if n_horizontal_cells_using_ideal as f32 / (min_horizontal_cells as f32)
+      < n_vertical_cells_using_ideal as f32 / (min_vertical_cells as f32)
     {
       // horizontal direction is more limiting
-      let cell_size_horizontal_fits = width / MIN_HORIZONTAL_CELLS;
+      let cell_size_horizontal_fits = (width / min_horizontal_cells).max(1);
       let n_vertical_cells_horizontal_fits = height / cell_size_horizontal_fits;
       (
-        GridBounds::from_size(MIN_HORIZONTAL_CELLS, n_vertical_cells_horizontal_fits),
+        GridBounds::from_size(min_horizontal_cells, n_vertical_cells_horizontal_fits),
         CellSize(cell_size_horizontal_fits as f32),
       )
     } else {
       // vertical direction is more limiting
-      let cell_size_vertical_fits = height / MIN_VERTICAL_CELLS;
+      let cell_size_vertical_fits = (height / min_vertical_cells).max(1);
       let n_horizontal_cells_vertical_fits = width / cell_size_vertical_fits;
       (
-        GridBounds::from_size(n_horizontal_cells_vertical_fits, MIN_VERTICAL_CELLS),
+        GridBounds::from_size(n_horizontal_cells_vertical_fits, min_vertical_cells),
         CellSize(cell_size_vertical_fits as f32),
       )
     }
   } else {
     (
       GridBounds::from_size(n_horizontal_cells_using_ideal, n_vertical_cells_using_ideal),
-      CellSize(CELL_SIZE_IDEAL as f32),
+      CellSize(cell_size_ideal as f32),
     )
   }
 }
+
+fn compute_hex_grid_bounds_for_available_space(
+  width: usize,
+  height: usize,
+  r_grid_sizing_config: &GridSizingConfig,
+) -> (GridBounds, CellSize) {
+  if let GridSizingMode::FixedCellCount { columns, rows } = r_grid_sizing_config.mode {
+    // the cell count is set in stone, so only the cell size can give (except that an odd number of
+    // rows is rounded up, see below)
+    let rows = rows.max(1).next_multiple_of(2);
+    let mut grid_bounds = GridBounds::from_size(columns.max(1), rows);
+    grid_bounds.set_neighbourhood(Neighbourhood::Hexagonal);
+    let cell_size = compute_cell_size_for_grid_bounds(&grid_bounds, width, height);
+    return (grid_bounds, cell_size);
+  }
+
+  // rows of hexagons interlock, so more of them fit in the same height
+  let (grid_bounds, cell_size) = compute_grid_bounds_for_available_space(
+    width,
+    (height as f32 / HEX_ROW_SPACING) as usize,
+    r_grid_sizing_config,
+  );
+
+  // every other row sticks out half a cell to the right, which needs room too (unless there is
+  // only a single column left)
+  let mut n_columns = grid_bounds.width();
+  if (n_columns as f32 + 0.5) * cell_size.0 > width as f32 {
+    n_columns = n_columns.saturating_sub(1).max(1);
+  }
+  // an even number of rows keeps the shifted rows lined up when wrapping around, so there are at
+  // least two of them
+  let n_rows = (grid_bounds.height() - grid_bounds.height() % 2).max(2);
+
+  (GridBounds::from_size(n_columns, n_rows), cell_size)
+}
+
+/// The largest cell size at which the whole grid fits in the available space. Large worlds may not
+/// fit at all, in which case you'll have to zoom out the camera.
+fn compute_cell_size_for_grid_bounds(
+  grid_bounds: &GridBounds,
+  width: usize,
+  height: usize,
+) -> CellSize {
+  let size = grid_bounds.world_size(1.0);
+  let cell_size = (width as f32 / size.x).min(height as f32 / size.y).floor();
+  CellSize(cell_size.max(MIN_CELL_SIZE as f32))
+}
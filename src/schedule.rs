@@ -1,23 +1,216 @@
 use core::time::Duration;
 
-use bevy::{prelude::*, time::common_conditions::on_timer};
+use bevy::app::RunFixedMainLoopSystem;
+use bevy::ecs::schedule::{ExecutorKind, ScheduleLabel};
+use bevy::prelude::*;
 
 pub fn schedule_plugin(app: &mut App) {
-  // configure a system set that runs every `TICK_DURATION`
+  // the simulation runs in its own schedules, a whole number of ticks per frame (see
+  // `SimulationClock`), single-threaded so that systems don't race each other
+  for label in [BehaveTick.intern(), SimulationTick.intern()] {
+    app.init_schedule(label);
+    app.edit_schedule(label, |schedule| {
+      schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+  }
   app
-    .configure_sets(Update, (TickSet,).chain().run_if(on_timer(TICK_DURATION)))
+    .init_resource::<SimulationClock>()
+    .init_resource::<SimTick>()
     .configure_sets(
-      Update,
-      (HungerTickSet,)
-        .chain()
-        .run_if(on_timer(HUNGER_TICK_DURATION)),
-    );
+      SimulationTick,
+      (
+        // (bevy's order for systems that aren't ordered differs between runs of the app, so every
+        // part of a tick is ordered explicitly, so that a seed always plays out the same way)
+        (
+          PrepareTickSet::Walls,
+          PrepareTickSet::Facing,
+          PrepareTickSet::Indices,
+          PrepareTickSet::Hunger,
+          PrepareTickSet::Points,
+          PrepareTickSet::MovementBehaviour,
+          PrepareTickSet::PickUpBehaviour,
+        )
+          .chain(),
+        (
+          TickSet::FruitSpawning,
+          TickSet::CoinSpawning,
+          TickSet::NaiveWalking,
+          TickSet::Walking,
+          TickSet::TargetFinding,
+          TickSet::PickingUp,
+        )
+          .chain(),
+        HungerTickSet
+          .run_if(|r_sim_tick: Res<SimTick>| r_sim_tick.0.is_multiple_of(HUNGER_TICK_INTERVAL)),
+      )
+        .chain(),
+    )
+    .add_systems(PreUpdate, advance_simulation_clock)
+    .add_systems(
+      RunFixedMainLoop,
+      run_simulation_ticks.in_set(RunFixedMainLoopSystem::FixedMainLoop),
+    )
+    .add_observer(toggle_pause)
+    .add_observer(step_simulation)
+    .add_observer(set_simulation_speed);
 }
 
-#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
-pub struct TickSet;
+/// Counts how many ticks are due. The frame time is virtual time, which bevy caps (see
+/// `Time::<Virtual>::max_delta`), so that a hitch or a tab that was in the background doesn't make
+/// the next frame run hundreds of ticks to catch up.
+fn advance_simulation_clock(mut r_clock: ResMut<SimulationClock>, r_time: Res<Time<Virtual>>) {
+  let delta = if r_clock.paused {
+    Duration::ZERO
+  } else {
+    r_time.delta().mul_f32(r_clock.speed)
+  };
+  let clock = r_clock.as_mut();
+  clock.tick_timer.tick(delta);
+  clock.due_ticks += clock.tick_timer.times_finished_this_tick();
+
+  // single steps advance the world by exactly one tick
+  clock.due_ticks += clock.pending_steps;
+  clock.pending_steps = 0;
+}
+
+/// Runs the ticks that are due this frame. Every tick, the behaviour trees are ticked (a few
+/// times, so that they can settle on new tasks), and then the tasks are processed.
+fn run_simulation_ticks(world: &mut World) {
+  let due_ticks = core::mem::take(&mut world.resource_mut::<SimulationClock>().due_ticks);
+  for _ in 0..due_ticks {
+    world.resource_mut::<SimTick>().0 += 1;
+
+    // the simulation sees the time advance by a whole tick, regardless of the frame rate
+    let simulation_time = {
+      let mut r_clock = world.resource_mut::<SimulationClock>();
+      r_clock.time.advance_by(TICK_DURATION);
+      r_clock.time
+    };
+    let frame_time = core::mem::replace(world.resource_mut::<Time>().as_mut(), simulation_time);
+
+    for _ in 0..BEHAVE_TICKS_PER_TICK {
+      world.run_schedule(BehaveTick);
+    }
+    world.run_schedule(SimulationTick);
+
+    *world.resource_mut::<Time>() = frame_time;
+  }
+}
+
+fn toggle_pause(_trigger: Trigger<TogglePause>, mut r_clock: ResMut<SimulationClock>) {
+  r_clock.paused = !r_clock.paused;
+}
+
+fn step_simulation(_trigger: Trigger<StepSimulation>, mut r_clock: ResMut<SimulationClock>) {
+  // stepping only makes sense in a frozen world
+  r_clock.paused = true;
+  r_clock.pending_steps += 1;
+}
+
+fn set_simulation_speed(
+  trigger: Trigger<SetSimulationSpeed>,
+  mut r_clock: ResMut<SimulationClock>,
+) {
+  let speed = trigger.event().0;
+  // (the clock can't run at NaN or infinite speed)
+  if !speed.is_finite() {
+    warn!("ignoring simulation speed {}", speed);
+    return;
+  }
+  r_clock.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+}
+
+/// The behaviour trees are ticked in this schedule, right before every `SimulationTick`.
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct BehaveTick;
+/// How often the trees are ticked per simulation tick. A tree moves on to its next node in the
+/// tick after its current node finished, so this is how many nodes it can get through per tick.
+const BEHAVE_TICKS_PER_TICK: usize = 4;
+
+/// All systems that change the simulated world run in this schedule, once per tick.
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SimulationTick;
+
+/// Runs at the start of every tick, to catch up with what changed since the last one: agents that
+/// spawned since the last tick get what they need (hunger, points and behaviours). The parts run
+/// in the order they are listed in.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PrepareTickSet {
+  /// Moves things out of walls that were spawned on top of them.
+  Walls,
+  Facing,
+  Indices,
+  Hunger,
+  Points,
+  MovementBehaviour,
+  PickUpBehaviour,
+}
+
+/// Processes the tasks that the behaviour trees are running. The parts run in the order they are
+/// listed in.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum TickSet {
+  FruitSpawning,
+  CoinSpawning,
+  NaiveWalking,
+  Walking,
+  TargetFinding,
+  PickingUp,
+}
 const TICK_DURATION: Duration = Duration::from_millis(600);
 
+/// Runs every `HUNGER_TICK_INTERVAL` ticks.
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct HungerTickSet;
-const HUNGER_TICK_DURATION: Duration = Duration::from_secs(3);
+const HUNGER_TICK_INTERVAL: u64 = 5;
+
+const MIN_SPEED: f32 = 0.1;
+const MAX_SPEED: f32 = 16.0;
+
+/// The number of ticks the simulation has run so far.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimTick(pub u64);
+
+/// Decides how many simulation ticks run every frame. The simulation can be paused, sped up or
+/// slowed down, and advanced one tick at a time.
+#[derive(Resource)]
+pub struct SimulationClock {
+  paused: bool,
+  speed: f32,
+  pending_steps: u32,
+  tick_timer: Timer,
+  due_ticks: u32,
+  /// The time as seen from inside the simulation.
+  time: Time,
+}
+
+impl Default for SimulationClock {
+  fn default() -> Self {
+    Self {
+      paused: false,
+      speed: 1.0,
+      pending_steps: 0,
+      tick_timer: Timer::new(TICK_DURATION, TimerMode::Repeating),
+      due_ticks: 0,
+      time: Time::default(),
+    }
+  }
+}
+
+impl SimulationClock {
+  /// How much real time there is between two ticks at the current speed.
+  pub fn tick_duration(&self) -> Duration {
+    TICK_DURATION.div_f32(self.speed)
+  }
+}
+
+#[derive(Event)]
+pub struct TogglePause;
+
+/// Pauses the simulation (if it wasn't already), and advances it by a single tick.
+#[derive(Event)]
+pub struct StepSimulation;
+
+/// Sets how many times faster than normal the simulation runs.
+#[derive(Event)]
+pub struct SetSimulationSpeed(pub f32);
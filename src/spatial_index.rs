@@ -0,0 +1,124 @@
+use core::marker::PhantomData;
+
+use bevy::ecs::component::ComponentId;
+use bevy::ecs::world::DeferredWorld;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::{
+  agent::Agent,
+  coins::Coin,
+  fruit::Fruit,
+  grid::{GridBounds, GridCell},
+  schedule::{PrepareTickSet, SimulationTick},
+};
+
+pub fn spatial_index_plugin(app: &mut App) {
+  app.add_plugins((
+    spatial_index_for::<Fruit>,
+    spatial_index_for::<Coin>,
+    spatial_index_for::<Agent>,
+  ));
+}
+
+/// Keeps a [`SpatialIndex`] of all entities with a `T` component.
+fn spatial_index_for<T: Component>(app: &mut App) {
+  app.init_resource::<SpatialIndex<T>>().add_systems(
+    SimulationTick,
+    update_spatial_index::<T>.in_set(PrepareTickSet::Indices),
+  );
+  app
+    .world_mut()
+    .register_component_hooks::<T>()
+    .on_remove(remove_from_spatial_index::<T>);
+}
+
+fn update_spatial_index<T: Component>(
+  q_moved: Query<(Entity, &GridCell), (With<T>, Changed<GridCell>)>,
+  mut r_index: ResMut<SpatialIndex<T>>,
+) {
+  for (e, cell) in q_moved.iter() {
+    r_index.insert(e, *cell);
+  }
+}
+
+fn remove_from_spatial_index<T: Component>(
+  mut world: DeferredWorld,
+  entity: Entity,
+  _component_id: ComponentId,
+) {
+  world.resource_mut::<SpatialIndex<T>>().remove(entity);
+}
+
+/// The entities with a `T` component, bucketed by the grid cell they are on.
+#[derive(Resource)]
+pub struct SpatialIndex<T> {
+  buckets: HashMap<GridCell, Vec<Entity>>,
+  cells: HashMap<Entity, GridCell>,
+  _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for SpatialIndex<T> {
+  fn default() -> Self {
+    Self {
+      buckets: HashMap::default(),
+      cells: HashMap::default(),
+      _marker: PhantomData,
+    }
+  }
+}
+
+impl<T> SpatialIndex<T> {
+  fn insert(&mut self, entity: Entity, cell: GridCell) {
+    if let Some(previous) = self.cells.insert(entity, cell) {
+      if previous == cell {
+        return;
+      }
+      self.remove_from_bucket(entity, &previous);
+    }
+    self.buckets.entry(cell).or_default().push(entity);
+  }
+
+  fn remove(&mut self, entity: Entity) {
+    if let Some(previous) = self.cells.remove(&entity) {
+      self.remove_from_bucket(entity, &previous);
+    }
+  }
+
+  fn remove_from_bucket(&mut self, entity: Entity, cell: &GridCell) {
+    let Some(bucket) = self.buckets.get_mut(cell) else {
+      return;
+    };
+    bucket.retain(|&e| e != entity);
+    if bucket.is_empty() {
+      self.buckets.remove(cell);
+    }
+  }
+
+  /// The entities on this cell.
+  pub fn at(&self, cell: &GridCell) -> impl Iterator<Item = Entity> + '_ {
+    self.buckets.get(cell).into_iter().flatten().copied()
+  }
+
+  /// The entities (and their cells) in the cells that can be within `radius` of `center` (see
+  /// [`GridBounds::cells_around`]). In wrap mode, the cells may reach across the edges, in which
+  /// case they are given as seen from `center` (i.e. not wrapped onto the grid).
+  pub fn within<'a>(
+    &'a self,
+    center: &GridCell,
+    radius: usize,
+    r_grid_bounds: &'a GridBounds,
+  ) -> impl Iterator<Item = (Entity, GridCell)> + 'a {
+    r_grid_bounds
+      .cells_around(center, radius)
+      .into_iter()
+      .flat_map(move |cell| {
+        self
+          .buckets
+          .get(&r_grid_bounds.wrap(&cell))
+          .into_iter()
+          .flatten()
+          .map(move |&e| (e, cell))
+      })
+  }
+}
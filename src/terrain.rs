@@ -0,0 +1,144 @@
+use bevy::color::palettes::tailwind as tw;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+
+use crate::{
+  entropy::{RngStream, TerrainScattering},
+  grid::{GridBounds, GridCell},
+};
+
+pub fn terrain_plugin(app: &mut App) {
+  app
+    .init_resource::<TerrainMap>()
+    .add_observer(scatter_terrain)
+    .add_observer(clear_terrain);
+}
+
+/// Paints a road straight across the grid, and some mud and water patches around it.
+fn scatter_terrain(
+  _trigger: Trigger<ScatterTerrain>,
+  r_grid_bounds: Res<GridBounds>,
+  mut r_terrain_map: ResMut<TerrainMap>,
+  mut rng: ResMut<RngStream<TerrainScattering>>,
+) {
+  r_terrain_map.0.clear();
+
+  for (terrain, n_patches) in [(Terrain::Mud, 4), (Terrain::Water, 2)] {
+    for _ in 0..n_patches {
+      // (an empty grid has no room for terrain)
+      let Some(center) = r_grid_bounds.get_random_position(&mut *rng) else {
+        return;
+      };
+      let radius = rng.gen_range(1..=3);
+      for x in (center.x - radius)..=(center.x + radius) {
+        for y in (center.y - radius)..=(center.y + radius) {
+          let cell = GridCell::new(x, y);
+          if r_grid_bounds.contains(&cell) && center.distance(&cell) <= radius as f32 + 0.5 {
+            r_terrain_map.0.insert(r_grid_bounds.wrap(&cell), terrain);
+          }
+        }
+      }
+    }
+  }
+
+  let Some(GridCell { y: road_y, .. }) = r_grid_bounds.get_random_position(&mut *rng) else {
+    return;
+  };
+  for x in r_grid_bounds.left_inclusive()..r_grid_bounds.right_exclusive() {
+    r_terrain_map
+      .0
+      .insert(GridCell::new(x, road_y), Terrain::Road);
+  }
+}
+
+fn clear_terrain(_trigger: Trigger<ClearTerrain>, mut r_terrain_map: ResMut<TerrainMap>) {
+  r_terrain_map.0.clear();
+}
+
+#[derive(Default, PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Terrain {
+  #[default]
+  Grass,
+  Mud,
+  Water,
+  Road,
+}
+
+impl Terrain {
+  pub const ALL: [Terrain; 4] = [Terrain::Grass, Terrain::Mud, Terrain::Water, Terrain::Road];
+
+  /// The number of ticks it takes to step off a cell with this terrain.
+  pub fn movement_cost(&self) -> usize {
+    match self {
+      Terrain::Grass => 1,
+      Terrain::Mud => 3,
+      Terrain::Water => 5,
+      Terrain::Road => 1,
+    }
+  }
+
+  pub fn color(&self) -> Color {
+    Color::from(match self {
+      Terrain::Grass => tw::GRAY_600,
+      Terrain::Mud => tw::AMBER_900,
+      Terrain::Water => tw::SKY_800,
+      Terrain::Road => tw::STONE_400,
+    })
+  }
+}
+
+/// The terrain of every cell on the grid. Cells that are not in the map are grass.
+#[derive(Resource, Default)]
+pub struct TerrainMap(HashMap<GridCell, Terrain>);
+
+impl TerrainMap {
+  pub fn get(&self, grid_cell: &GridCell) -> Terrain {
+    self.0.get(grid_cell).copied().unwrap_or_default()
+  }
+}
+
+impl From<HashMap<GridCell, Terrain>> for TerrainMap {
+  fn from(value: HashMap<GridCell, Terrain>) -> Self {
+    Self(value)
+  }
+}
+
+/// How much a spawner favours each terrain, as a probability of accepting a cell with that
+/// terrain. Terrains without a weight are always accepted.
+#[derive(Default, Clone)]
+pub struct TerrainWeights(HashMap<Terrain, f64>);
+
+impl TerrainWeights {
+  pub fn with(mut self, terrain: Terrain, weight: f64) -> Self {
+    self.0.insert(terrain, weight.clamp(0.0, 1.0));
+    self
+  }
+
+  pub fn weight(&self, terrain: Terrain) -> f64 {
+    self.0.get(&terrain).copied().unwrap_or(1.0)
+  }
+}
+
+/// Counts down the ticks until an agent may take its next step, based on the terrain it is on.
+#[derive(Component, Default)]
+pub struct StepCooldown(usize);
+
+impl StepCooldown {
+  /// Counts down a tick, and returns whether the agent may take a step during this tick.
+  pub fn tick(&mut self) -> bool {
+    self.0 = self.0.saturating_sub(1);
+    self.0 == 0
+  }
+
+  /// Starts the cooldown after stepping onto a cell with the given terrain.
+  pub fn start(&mut self, terrain: Terrain) {
+    self.0 = terrain.movement_cost();
+  }
+}
+
+#[derive(Event)]
+pub struct ScatterTerrain;
+
+#[derive(Event)]
+pub struct ClearTerrain;
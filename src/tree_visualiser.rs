@@ -0,0 +1,301 @@
+use bevy::color::palettes::tailwind as tw;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_behave::prelude::*;
+
+use crate::agent::SelectedAgent;
+use crate::schedule::BehaveTick;
+
+/// Shows the behaviour trees of the selected agent in a panel: which nodes are running, and how
+/// every node ended the last time it ran.
+pub fn tree_visualiser_plugin(app: &mut App) {
+  app
+    .init_resource::<TreeVisualiserEnabled>()
+    .init_resource::<NodeResults>()
+    .add_systems(Startup, spawn_panel)
+    // (a node can start and finish within a single tick, so its result is recorded right after
+    // every time the trees are ticked)
+    .add_systems(BehaveTick, record_node_results.after(BehaveSet))
+    .add_systems(Update, update_panel)
+    .add_observer(on_toggle_tree_visualiser);
+}
+
+fn on_toggle_tree_visualiser(
+  _trigger: Trigger<ToggleTreeVisualiser>,
+  mut r_tree_visualiser_enabled: ResMut<TreeVisualiserEnabled>,
+  mut q_panel: Single<&mut Visibility, With<TreePanel>>,
+) {
+  r_tree_visualiser_enabled.0 = !r_tree_visualiser_enabled.0;
+  **q_panel = if r_tree_visualiser_enabled.0 {
+    Visibility::Inherited
+  } else {
+    Visibility::Hidden
+  };
+}
+
+fn spawn_panel(mut commands: Commands) {
+  commands.spawn((
+    TreePanel,
+    Node {
+      position_type: PositionType::Absolute,
+      top: Val::Px(8.0),
+      left: Val::Px(8.0),
+      padding: UiRect::all(Val::Px(8.0)),
+      ..default()
+    },
+    BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+    Text::default(),
+    TextFont::from_font_size(14.0),
+    Visibility::Hidden,
+  ));
+}
+
+fn record_node_results(
+  q_children: Query<&Children>,
+  q_trees: Query<&BehaveTree>,
+  r_tree_visualiser_enabled: Res<TreeVisualiserEnabled>,
+  r_selected_agent: Res<SelectedAgent>,
+  mut r_node_results: ResMut<NodeResults>,
+) {
+  if !r_tree_visualiser_enabled.0 {
+    return;
+  }
+  let trees = r_selected_agent
+    .0
+    .and_then(|agent| q_children.get(agent).ok())
+    .into_iter()
+    .flatten()
+    .filter(|child| q_trees.contains(**child))
+    .copied()
+    .collect::<Vec<_>>();
+  // (forgets the trees of the previous selection, and trees that were replaced)
+  r_node_results.0.retain(|tree, _| trees.contains(tree));
+
+  for tree in trees {
+    let Ok(behave_tree) = q_trees.get(tree) else {
+      continue;
+    };
+    let nodes = TreeNode::parse(behave_tree);
+    let results = r_node_results.0.entry(tree).or_default();
+    results.resize(nodes.len(), None);
+    for (result, node) in results.iter_mut().zip(nodes) {
+      match node.status {
+        NodeStatus::Success => *result = Some(true),
+        NodeStatus::Failure => *result = Some(false),
+        NodeStatus::Idle | NodeStatus::Running | NodeStatus::PendingReset => {}
+      }
+    }
+  }
+}
+
+/// Writes out the selected agent's trees, one line per node, coloured by how the node is doing.
+fn update_panel(
+  q_panel: Single<Entity, With<TreePanel>>,
+  q_children: Query<&Children>,
+  q_trees: Query<(&BehaveTree, &Name)>,
+  r_tree_visualiser_enabled: Res<TreeVisualiserEnabled>,
+  r_selected_agent: Res<SelectedAgent>,
+  r_node_results: Res<NodeResults>,
+  mut shown: Local<Vec<(String, Color)>>,
+  mut commands: Commands,
+) {
+  if !r_tree_visualiser_enabled.0 {
+    return;
+  }
+
+  let mut lines = Vec::new();
+  let trees = r_selected_agent
+    .0
+    .and_then(|agent| q_children.get(agent).ok())
+    .into_iter()
+    .flatten()
+    .filter_map(|child| Some((*child, q_trees.get(*child).ok()?)));
+  for (tree, (behave_tree, name)) in trees {
+    lines.push((format!("{name}\n"), Color::WHITE));
+    let results = r_node_results.0.get(&tree);
+    for (i, node) in TreeNode::parse(behave_tree).into_iter().enumerate() {
+      let last_result = results.and_then(|results| results.get(i).copied().flatten());
+      let (marker, color) = match (node.status, last_result) {
+        (NodeStatus::Running, _) => (">", tw::YELLOW_300),
+        (_, Some(true)) => ("+", tw::GREEN_400),
+        (_, Some(false)) => ("x", tw::RED_400),
+        (_, None) => ("-", tw::GRAY_400),
+      };
+      let indent = "  ".repeat(node.depth + 1);
+      lines.push((format!("{indent}{marker} {}\n", node.label), color.into()));
+    }
+  }
+  if lines.is_empty() {
+    let hint = if r_selected_agent.0.is_some() {
+      "the selected agent has no behaviour trees"
+    } else {
+      "no agent selected"
+    };
+    lines.push((hint.to_string(), tw::GRAY_400.into()));
+  }
+
+  // (respawning the text every frame would be wasteful, it only changes when a node does)
+  if *shown == lines {
+    return;
+  }
+  commands
+    .entity(*q_panel)
+    .despawn_descendants()
+    .with_children(|panel| {
+      for (line, color) in &lines {
+        panel.spawn((
+          TextSpan::new(line.clone()),
+          TextFont::from_font_size(14.0),
+          TextColor(*color),
+        ));
+      }
+    });
+  *shown = lines;
+}
+
+/// A node of a `BehaveTree`, as far as it can be seen from outside of `bevy_behave`.
+struct TreeNode {
+  depth: usize,
+  label: String,
+  status: NodeStatus,
+}
+
+impl TreeNode {
+  /// Reads the nodes (depth first) from how `bevy_behave` displays a tree, which is the only way
+  /// it shows how its nodes are doing. Every line of that looks like
+  /// `    * DynamicEntity(Find fruit) --> ⏳  [NodeId(3)]`. (That format isn't part of
+  /// `bevy_behave`'s API, which is why its version is pinned.)
+  fn parse(tree: &BehaveTree) -> Vec<Self> {
+    Self::parse_lines(&tree.to_string())
+  }
+
+  fn parse_lines(displayed_tree: &str) -> Vec<Self> {
+    displayed_tree
+      .lines()
+      .filter_map(|line| {
+        let node = line.trim_start();
+        let depth = (line.len() - node.len()) / 2;
+        let node = node.strip_prefix("* ")?;
+        let node = node.rsplit_once("  [").map_or(node, |(node, _id)| node);
+        let (node, status) = match node.split_once(" --> ") {
+          Some((node, "✅")) => (node, NodeStatus::Success),
+          Some((node, "❌")) => (node, NodeStatus::Failure),
+          Some((node, "🔄")) => (node, NodeStatus::PendingReset),
+          Some((node, _)) => (node, NodeStatus::Running),
+          None => (node, NodeStatus::Idle),
+        };
+        Some(Self {
+          depth,
+          label: Self::label(node),
+          status,
+        })
+      })
+      .collect()
+  }
+
+  /// Leaves are shown by their name, and triggers by their type (without its path).
+  fn label(node: &str) -> String {
+    match node.split_once('(') {
+      Some(("DynamicEntity", name)) => name.strip_suffix(')').unwrap_or(name).to_string(),
+      Some(("TriggerReq", type_name)) => type_name
+        .strip_suffix(')')
+        .unwrap_or(type_name)
+        .rsplit("::")
+        .next()
+        .unwrap_or(type_name)
+        .to_string(),
+      _ => match node {
+        "SequenceFlow" => "Sequence".to_string(),
+        "FallbackFlow" => "Fallback".to_string(),
+        _ => node.to_string(),
+      },
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum NodeStatus {
+  /// Not run since the tree (or its branch) last started over.
+  Idle,
+  Running,
+  Success,
+  Failure,
+  /// Finished, and about to start over.
+  PendingReset,
+}
+
+/// How every node of the selected agent's trees ended the last time it ran (by tree, in the order
+/// of [`TreeNode::parse`]).
+#[derive(Resource, Default)]
+struct NodeResults(HashMap<Entity, Vec<Option<bool>>>);
+
+#[derive(Component)]
+struct TreePanel;
+
+#[derive(Resource, Default)]
+pub struct TreeVisualiserEnabled(pub bool);
+
+#[derive(Event)]
+pub struct ToggleTreeVisualiser;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Clone)]
+  struct IsHungry;
+
+  fn summary(nodes: &[TreeNode]) -> Vec<(usize, &str, NodeStatus)> {
+    nodes
+      .iter()
+      .map(|node| (node.depth, node.label.as_str(), node.status))
+      .collect()
+  }
+
+  #[test]
+  fn parses_displayed_tree() {
+    let tree = BehaveTree::new(behave! {
+      Behave::Forever => {
+        Behave::Fallback => {
+          Behave::trigger(IsHungry),
+          Behave::Sequence => {
+            Behave::spawn_named("Find fruit (nearby)", ()),
+            Behave::Wait(1.0),
+          }
+        }
+      }
+    });
+
+    assert_eq!(
+      summary(&TreeNode::parse(&tree)),
+      [
+        (0, "Forever", NodeStatus::Idle),
+        (1, "Fallback", NodeStatus::Idle),
+        (2, "IsHungry", NodeStatus::Idle),
+        (2, "Sequence", NodeStatus::Idle),
+        (3, "Find fruit (nearby)", NodeStatus::Idle),
+        (3, "Wait(1)", NodeStatus::Idle),
+      ]
+    );
+  }
+
+  #[test]
+  fn parses_node_statuses() {
+    let nodes = TreeNode::parse_lines(concat!(
+      "* Forever --> ⏳  [NodeId(1)]\n",
+      "  * SequenceFlow --> 🔄  [NodeId(2)]\n",
+      "    * DynamicEntity(Go (fast)) --> ✅  [NodeId(3)]\n",
+      "    * AlwaysFail --> ❌  [NodeId(4)]\n",
+    ));
+
+    assert_eq!(
+      summary(&nodes),
+      [
+        (0, "Forever", NodeStatus::Running),
+        (1, "Sequence", NodeStatus::PendingReset),
+        (2, "Go (fast)", NodeStatus::Success),
+        (2, "AlwaysFail", NodeStatus::Failure),
+      ]
+    );
+  }
+}
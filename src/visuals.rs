@@ -0,0 +1,193 @@
+use bevy::color::palettes::tailwind as tw;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::{
+  agent::Agent,
+  coins::Coin,
+  fruit::Fruit,
+  grid::{GridBounds, GridCell, Ground, Neighbourhood},
+  terrain::{Terrain, TerrainMap},
+  walls::Wall,
+};
+
+/// Draws the simulated world. Everything it draws with comes from the shared [`Visuals`], and is
+/// attached as entities are spawned, so that the simulation itself doesn't need to know about
+/// meshes and materials (and can run without them).
+pub fn visuals_plugin(app: &mut App) {
+  app
+    .init_resource::<Visuals>()
+    .add_systems(
+      Update,
+      recolour_ground.run_if(resource_changed::<TerrainMap>),
+    )
+    .add_observer(add_ground_visuals)
+    .add_observer(add_agent_visuals)
+    .add_observer(add_fruit_visuals)
+    .add_observer(add_coin_visuals)
+    .add_observer(add_wall_visuals);
+}
+
+fn add_ground_visuals(
+  trigger: Trigger<OnAdd, Ground>,
+  q_ground: Query<&GridCell>,
+  r_grid_bounds: Res<GridBounds>,
+  r_terrain_map: Res<TerrainMap>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  let terrain = q_ground
+    .get(trigger.entity())
+    .map(|cell| r_terrain_map.get(cell))
+    .unwrap_or_default();
+  commands.entity(trigger.entity()).insert((
+    Mesh2d(r_visuals.ground_mesh(&r_grid_bounds)),
+    MeshMaterial2d(r_visuals.terrain_material(terrain)),
+  ));
+}
+
+fn add_agent_visuals(
+  trigger: Trigger<OnAdd, Agent>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  commands.entity(trigger.entity()).insert((
+    Mesh2d(r_visuals.agent_mesh.clone()),
+    MeshMaterial2d(r_visuals.agent_material.clone()),
+  ));
+}
+
+fn add_fruit_visuals(
+  trigger: Trigger<OnAdd, Fruit>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  commands.entity(trigger.entity()).insert((
+    Mesh2d(r_visuals.fruit_mesh.clone()),
+    MeshMaterial2d(r_visuals.fruit_material.clone()),
+  ));
+}
+
+fn add_coin_visuals(
+  trigger: Trigger<OnAdd, Coin>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  commands.entity(trigger.entity()).insert((
+    Mesh2d(r_visuals.coin_mesh.clone()),
+    MeshMaterial2d(r_visuals.coin_material.clone()),
+  ));
+}
+
+fn add_wall_visuals(
+  trigger: Trigger<OnAdd, Wall>,
+  r_visuals: Res<Visuals>,
+  mut commands: Commands,
+) {
+  commands.entity(trigger.entity()).insert((
+    Mesh2d(r_visuals.wall_mesh.clone()),
+    MeshMaterial2d(r_visuals.wall_material.clone()),
+  ));
+}
+
+fn recolour_ground(
+  mut q_ground: Query<(&GridCell, &mut MeshMaterial2d<ColorMaterial>), With<Ground>>,
+  r_terrain_map: Res<TerrainMap>,
+  r_visuals: Res<Visuals>,
+) {
+  for (cell, mut material) in q_ground.iter_mut() {
+    let terrain_material = r_visuals.terrain_material(r_terrain_map.get(cell));
+    if material.0 != terrain_material {
+      material.0 = terrain_material;
+    }
+  }
+}
+
+/// The meshes and materials that all entities share, so that spawning things (or respawning the
+/// whole grid on a resize) doesn't add new assets every time.
+#[derive(Resource)]
+pub struct Visuals {
+  square_cell: Handle<Mesh>,
+  square_ground: Handle<Mesh>,
+  hex_cell: Handle<Mesh>,
+  hex_ground: Handle<Mesh>,
+  agent_mesh: Handle<Mesh>,
+  fruit_mesh: Handle<Mesh>,
+  coin_mesh: Handle<Mesh>,
+  wall_mesh: Handle<Mesh>,
+  /// A bar that scales from its bottom edge.
+  pub indicator_mesh: Handle<Mesh>,
+  terrain_materials: HashMap<Terrain, Handle<ColorMaterial>>,
+  agent_material: Handle<ColorMaterial>,
+  fruit_material: Handle<ColorMaterial>,
+  coin_material: Handle<ColorMaterial>,
+  wall_material: Handle<ColorMaterial>,
+  pub fog_material: Handle<ColorMaterial>,
+  pub hunger_material: Handle<ColorMaterial>,
+  pub points_material: Handle<ColorMaterial>,
+}
+
+impl Visuals {
+  /// A mesh that covers a whole cell.
+  pub fn cell_mesh(&self, grid_bounds: &GridBounds) -> Handle<Mesh> {
+    match grid_bounds.neighbourhood() {
+      Neighbourhood::Hexagonal => self.hex_cell.clone(),
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => self.square_cell.clone(),
+    }
+  }
+
+  /// A mesh that covers a cell, minus a gap to its neighbours.
+  pub fn ground_mesh(&self, grid_bounds: &GridBounds) -> Handle<Mesh> {
+    match grid_bounds.neighbourhood() {
+      Neighbourhood::Hexagonal => self.hex_ground.clone(),
+      Neighbourhood::VonNeumann | Neighbourhood::Moore => self.square_ground.clone(),
+    }
+  }
+
+  pub fn terrain_material(&self, terrain: Terrain) -> Handle<ColorMaterial> {
+    self.terrain_materials[&terrain].clone()
+  }
+}
+
+impl FromWorld for Visuals {
+  fn from_world(world: &mut World) -> Self {
+    let mut meshes = world.resource_mut::<Assets<Mesh>>();
+    let square_cell = meshes.add(Neighbourhood::VonNeumann.cell_mesh(1.0));
+    let square_ground = meshes.add(Neighbourhood::VonNeumann.cell_mesh(0.8));
+    let hex_cell = meshes.add(Neighbourhood::Hexagonal.cell_mesh(1.0));
+    let hex_ground = meshes.add(Neighbourhood::Hexagonal.cell_mesh(0.8));
+    let agent_mesh = meshes.add(Rectangle::new(0.9, 0.9));
+    let fruit_mesh = meshes.add(Rectangle::new(0.3, 0.3));
+    let coin_mesh = meshes.add(Circle::new(0.25));
+    let wall_mesh = meshes.add(Rectangle::new(1.0, 1.0));
+    // translated so that it scales from the side rather than from the center
+    let indicator_mesh =
+      meshes.add(Mesh::from(Rectangle::new(0.15, 0.8)).translated_by(Vec3::Y * 0.4));
+
+    let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+    let terrain_materials = Terrain::ALL
+      .into_iter()
+      .map(|terrain| (terrain, materials.add(terrain.color())))
+      .collect();
+
+    Self {
+      square_cell,
+      square_ground,
+      hex_cell,
+      hex_ground,
+      agent_mesh,
+      fruit_mesh,
+      coin_mesh,
+      wall_mesh,
+      indicator_mesh,
+      terrain_materials,
+      agent_material: materials.add(Color::from(tw::GREEN_600)),
+      fruit_material: materials.add(Color::from(tw::RED_600)),
+      coin_material: materials.add(Color::from(tw::YELLOW_400)),
+      wall_material: materials.add(Color::from(tw::GRAY_800)),
+      fog_material: materials.add(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+      hunger_material: materials.add(Color::from(tw::RED_500)),
+      points_material: materials.add(Color::from(tw::YELLOW_400)),
+    }
+  }
+}
@@ -0,0 +1,179 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use rand::Rng;
+
+use crate::{
+  grid::{GridBounds, GridCell, Ground, Neighbourhood},
+  pathfinding::find_nearest,
+  schedule::{PrepareTickSet, SimulationTick},
+  terrain::{Terrain, TerrainMap},
+};
+
+pub fn walls_plugin(app: &mut App) {
+  app
+    .init_resource::<Walls>()
+    .add_systems(
+      SimulationTick,
+      (update_walls_index, evict_from_walls)
+        .chain()
+        .in_set(PrepareTickSet::Walls),
+    )
+    .add_observer(spawn_walls)
+    .add_observer(clear_walls)
+    .add_observer(replace_walls);
+}
+
+/// Rebuilds the [`Walls`] index whenever walls are spawned or despawned.
+fn update_walls_index(
+  q_added_walls: Query<(), Added<Wall>>,
+  q_walls: Query<&GridCell, With<Wall>>,
+  mut removed_walls: RemovedComponents<Wall>,
+  mut r_walls: ResMut<Walls>,
+) {
+  if q_added_walls.is_empty() && removed_walls.read().count() == 0 {
+    return;
+  }
+  r_walls.0 = q_walls.iter().copied().collect();
+}
+
+/// Moves agents and items that ended up inside a wall (e.g. because walls were spawned on top of
+/// them) to the nearest free cell.
+fn evict_from_walls(
+  mut q_occupants: Query<&mut GridCell, (Without<Wall>, Without<Ground>)>,
+  r_walls: Res<Walls>,
+  r_grid_bounds: Res<GridBounds>,
+) {
+  for mut cell in q_occupants.iter_mut() {
+    if !(r_walls.is_changed() || cell.is_changed()) || !r_walls.contains(&cell) {
+      continue;
+    }
+    if let Some(free_cell) = find_nearest(&cell, &r_grid_bounds, |c| !r_walls.contains(c)) {
+      *cell = free_cell;
+    }
+  }
+}
+
+/// Divides the grid into four rooms, connected through a doorway in each dividing wall.
+fn spawn_walls(
+  _trigger: Trigger<SpawnWalls>,
+  r_grid_bounds: Res<GridBounds>,
+  mut commands: Commands,
+) {
+  let (left, right) = (
+    r_grid_bounds.left_inclusive(),
+    r_grid_bounds.right_exclusive(),
+  );
+  let (top, bottom) = (
+    r_grid_bounds.top_inclusive(),
+    r_grid_bounds.bottom_exclusive(),
+  );
+
+  let mut cells = Vec::new();
+  for x in left..right {
+    // leave a doorway halfway along each room
+    if x != (left - 1) / 2 && x != right / 2 {
+      cells.push(GridCell::new(x, 0));
+    }
+  }
+  for y in top..bottom {
+    if y != 0 && y != (top - 1) / 2 && y != bottom / 2 {
+      cells.push(GridCell::new(0, y));
+    }
+  }
+
+  commands.trigger(ReplaceWalls(cells));
+}
+
+fn clear_walls(_trigger: Trigger<ClearWalls>, mut commands: Commands) {
+  commands.trigger(ReplaceWalls(Vec::new()));
+}
+
+fn replace_walls(
+  trigger: Trigger<ReplaceWalls>,
+  q_walls: Query<Entity, With<Wall>>,
+  mut commands: Commands,
+) {
+  for e in q_walls.iter() {
+    // a resize may have despawned some of these already
+    commands.entity(e).try_despawn();
+  }
+
+  for cell in trigger.event().0.iter() {
+    commands.spawn((Wall, *cell));
+  }
+}
+
+/// An impassable grid cell.
+#[derive(Component)]
+#[require(Transform(|| Transform::from_xyz(0.0, 0.0, 0.05)), GridCell)]
+pub struct Wall;
+
+/// The cells that are currently occupied by a [`Wall`], for quick lookups.
+#[derive(Resource, Default)]
+pub struct Walls(HashSet<GridCell>);
+
+impl Walls {
+  pub fn contains(&self, grid_cell: &GridCell) -> bool {
+    self.0.contains(grid_cell)
+  }
+}
+
+/// Answers whether agents can move onto a cell, taking both the grid bounds and walls into account,
+/// and how long it takes them to move off it again.
+#[derive(SystemParam)]
+pub struct Walkability<'w> {
+  r_grid_bounds: Res<'w, GridBounds>,
+  r_walls: Res<'w, Walls>,
+  r_terrain_map: Res<'w, TerrainMap>,
+}
+
+impl Walkability<'_> {
+  pub fn is_walkable(&self, grid_cell: &GridCell) -> bool {
+    self.r_grid_bounds.contains(grid_cell)
+      && !self.r_walls.contains(&self.r_grid_bounds.wrap(grid_cell))
+  }
+
+  /// Whether an agent can step from one cell onto a neighbouring one. Diagonal steps can't cut
+  /// corners, so both cells alongside the step must be walkable too.
+  pub fn can_step(&self, from: &GridCell, to: &GridCell) -> bool {
+    if !self.is_walkable(to) {
+      return false;
+    }
+    let (dx, dy) = self.r_grid_bounds.offset(from, to);
+    // (only the diagonal steps on a square grid can squeeze past corners)
+    dx == 0
+      || dy == 0
+      || self.r_grid_bounds.neighbourhood() != Neighbourhood::Moore
+      || (self.is_walkable(&self.r_grid_bounds.step(from, (dx, 0)))
+        && self.is_walkable(&self.r_grid_bounds.step(from, (0, dy))))
+  }
+
+  pub fn grid_bounds(&self) -> &GridBounds {
+    &self.r_grid_bounds
+  }
+
+  pub fn terrain(&self, grid_cell: &GridCell) -> Terrain {
+    self.r_terrain_map.get(&self.r_grid_bounds.wrap(grid_cell))
+  }
+
+  /// Picks a random walkable cell, or `None` if we could not find one in a reasonable number of
+  /// attempts (e.g. when the grid is almost entirely walled off).
+  pub fn get_random_position(&self, rng: &mut impl Rng) -> Option<GridCell> {
+    (0..MAX_RANDOM_POSITION_ATTEMPTS)
+      .map_while(|_| self.r_grid_bounds.get_random_position(rng))
+      .find(|cell| self.is_walkable(cell))
+  }
+}
+
+const MAX_RANDOM_POSITION_ATTEMPTS: usize = 100;
+
+#[derive(Event)]
+pub struct SpawnWalls;
+
+#[derive(Event)]
+pub struct ClearWalls;
+
+/// Despawns all walls, and spawns new ones on the given cells.
+#[derive(Event)]
+pub struct ReplaceWalls(pub Vec<GridCell>);